@@ -0,0 +1,103 @@
+use std::{f64::consts::PI, hint::black_box};
+
+use bevy_math::DVec2;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use sun_orbit_simulation::{
+    constants::{AU, G, SUN_MASS},
+    math::{
+        collision::possible_contacts,
+        physics::{BodyState, DEFAULT_OPENING_ANGLE, ForceModel, GravitySolver, Integrator},
+    },
+};
+
+const ASTEROID_MASS: f64 = 1e18;
+const ASTEROID_RADIUS: f64 = 5e4; // m
+
+/// The sun and `count` asteroids on circular orbits, spread evenly over a disk from 1 to 5 AU.
+fn disk(count: usize) -> Vec<BodyState> {
+    let mut bodies = vec![BodyState {
+        mass: SUN_MASS,
+        position: DVec2::ZERO,
+        velocity: DVec2::ZERO,
+    }];
+
+    // sunflower pattern, even without random numbers
+    let golden_angle = PI * (3.0 - 5f64.sqrt());
+    for i in 0..count {
+        let distance = AU * (1.0 + 4.0 * (i as f64 / count as f64).sqrt());
+        let direction = DVec2::from_angle(i as f64 * golden_angle);
+        let speed = (G * SUN_MASS / distance).sqrt();
+        bodies.push(BodyState {
+            mass: ASTEROID_MASS,
+            position: direction * distance,
+            velocity: direction.perp() * speed,
+        });
+    }
+    bodies
+}
+
+/// One velocity Verlet step, what a physics tick costs at most once per substep.
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("verlet_step");
+    group.sample_size(10);
+
+    let barnes_hut = ForceModel {
+        solver: GravitySolver::BarnesHut {
+            opening_angle: DEFAULT_OPENING_ANGLE,
+        },
+        ..Default::default()
+    };
+    let relativity = ForceModel {
+        relativity: true,
+        ..barnes_hut
+    };
+    let solvers = [
+        ("direct", ForceModel::default(), 10_000),
+        ("barnes_hut", barnes_hut, 100_000),
+        ("barnes_hut_relativity", relativity, 100_000),
+    ];
+
+    for (name, forces, max_count) in solvers {
+        for count in [1_000, 10_000, 100_000] {
+            // direct summation of 100k bodies takes minutes per step
+            if count > max_count {
+                continue;
+            }
+
+            let bodies = disk(count);
+            group.bench_with_input(BenchmarkId::new(name, count), &bodies, |b, bodies| {
+                b.iter_batched_ref(
+                    || bodies.clone(),
+                    |bodies| Integrator::VelocityVerlet.step(3600.0, black_box(bodies), forces),
+                    criterion::BatchSize::LargeInput,
+                );
+            });
+        }
+    }
+
+    group.finish();
+}
+
+/// Looking for bodies that touched during an hour long tick, which the viewer does once per tick
+/// on top of the steps.
+fn contacts(c: &mut Criterion) {
+    let mut group = c.benchmark_group("possible_contacts");
+    group.sample_size(10);
+
+    for count in [1_000, 10_000, 100_000] {
+        let bodies = disk(count);
+        let previous = bodies
+            .iter()
+            .map(|body| body.position - body.velocity * 3600.0)
+            .collect::<Vec<_>>();
+        let radii = vec![ASTEROID_RADIUS; bodies.len()];
+        group.bench_with_input(BenchmarkId::from_parameter(count), &bodies, |b, bodies| {
+            b.iter(|| possible_contacts(black_box(bodies), &previous, &radii));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, step, contacts);
+criterion_main!(benches);
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, error::Error, fs, path::Path};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+// values for rendering, they're not used for physics
+pub const SUN_RADIUS: f32 = 3.0;
+pub const EARTH_RADIUS: f32 = 1.5;
+pub const MERCURY_RADIUS: f32 = EARTH_RADIUS * 0.3829;
+pub const VENUS_RADIUS: f32 = EARTH_RADIUS * 0.9499;
+pub const MARS_RADIUS: f32 = EARTH_RADIUS * 0.533;
+pub const JUPITER_RADIUS: f32 = 5.0;
+pub const SATURN_RADIUS: f32 = 3.5;
+pub const URANUS_RADIUS: f32 = 3.0;
+pub const NEPTUNE_RADIUS: f32 = 2.5;
+pub const MOON_RADIUS: f32 = EARTH_RADIUS * 0.2727;
+pub const IO_RADIUS: f32 = EARTH_RADIUS * 0.2859;
+pub const EUROPA_RADIUS: f32 = EARTH_RADIUS * 0.2450;
+pub const GANYMEDE_RADIUS: f32 = EARTH_RADIUS * 0.4135;
+pub const CALLISTO_RADIUS: f32 = EARTH_RADIUS * 0.3783;
+pub const TITAN_RADIUS: f32 = EARTH_RADIUS * 0.4041;
+
+// ring systems, radii are in radii of the planet
+pub const SATURN_RING_INNER: f32 = 1.239; // inner edge of the C ring
+pub const SATURN_RING_OUTER: f32 = 2.270; // outer edge of the A ring
+pub const SATURN_CASSINI_DIVISION: (f32, f32) = (1.951, 2.027);
+pub const SATURN_ENCKE_GAP: (f32, f32) = (2.213, 2.219);
+pub const URANUS_RING_INNER: f32 = 1.637;
+pub const URANUS_RING_OUTER: f32 = 2.001;
+pub const NEPTUNE_RING_INNER: f32 = 1.692;
+pub const NEPTUNE_RING_OUTER: f32 = 2.541;
+
+const DEFAULT_RADIUS: f32 = EARTH_RADIUS * 0.3; // bodies the scenario doesn't describe
+
+/// How a body is drawn, the simulation only knows its physical size.
+#[derive(Clone)]
+pub struct Appearance {
+    pub radius: f32, // of the mesh in world units
+    pub texture: Option<String>,
+    pub color: Color,
+    pub ring: Option<RingConfig>,
+}
+
+impl Default for Appearance {
+    fn default() -> Self {
+        Self {
+            radius: DEFAULT_RADIUS,
+            texture: None,
+            color: Color::WHITE,
+            ring: None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RingConfig {
+    pub inner_radius: f32, // in radii of the body
+    pub outer_radius: f32,
+    pub color: Color,
+    pub opacity: f32,
+    pub gaps: Vec<(f32, f32)>, // transparent divisions, in radii of the body
+    pub faint: bool,           // hidden unless enabled in the ui
+}
+
+/// Appearance of the scenario's bodies by name.
+#[derive(Resource, Default)]
+pub struct Appearances(HashMap<String, Appearance>);
+
+impl Appearances {
+    /// Textured planets and plainly colored moons, there are no textures for the moons.
+    pub fn solar_system() -> Self {
+        let planets = [
+            ("Mercury", MERCURY_RADIUS),
+            ("Venus", VENUS_RADIUS),
+            ("Earth", EARTH_RADIUS),
+            ("Mars", MARS_RADIUS),
+            ("Jupiter", JUPITER_RADIUS),
+            ("Saturn", SATURN_RADIUS),
+            ("Uranus", URANUS_RADIUS),
+            ("Neptune", NEPTUNE_RADIUS),
+        ]
+        .map(|(name, radius)| {
+            let appearance = Appearance {
+                radius,
+                texture: Some(format!("textures/{}.jpg", name.to_lowercase())),
+                color: Color::WHITE,
+                ring: planet_ring(name),
+            };
+            (name, appearance)
+        });
+
+        let moons = [
+            ("Moon", MOON_RADIUS, Color::srgb(0.6, 0.6, 0.6)),
+            ("Io", IO_RADIUS, Color::srgb(0.9, 0.8, 0.4)),
+            ("Europa", EUROPA_RADIUS, Color::srgb(0.85, 0.8, 0.7)),
+            ("Ganymede", GANYMEDE_RADIUS, Color::srgb(0.6, 0.55, 0.5)),
+            ("Callisto", CALLISTO_RADIUS, Color::srgb(0.4, 0.37, 0.33)),
+            ("Titan", TITAN_RADIUS, Color::srgb(0.85, 0.65, 0.3)),
+        ]
+        .map(|(name, radius, color)| {
+            let appearance = Appearance {
+                radius,
+                color,
+                ..Default::default()
+            };
+            (name, appearance)
+        });
+
+        Self(
+            planets
+                .into_iter()
+                .chain(moons)
+                .map(|(name, appearance)| (name.to_string(), appearance))
+                .collect(),
+        )
+    }
+
+    /// Reads the appearance fields of the bodies in a scenario file, the simulation reads the
+    /// rest of it.
+    pub fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file: AppearanceFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        Ok(Self(
+            file.bodies
+                .into_iter()
+                .map(|body| {
+                    let appearance = Appearance {
+                        radius: body.radius,
+                        texture: body.texture,
+                        color: srgb(body.color),
+                        ring: body.ring.map(|ring| RingConfig {
+                            inner_radius: ring.inner_radius,
+                            outer_radius: ring.outer_radius,
+                            color: srgb(ring.color),
+                            opacity: ring.opacity,
+                            gaps: ring.gaps,
+                            faint: ring.faint,
+                        }),
+                    };
+                    (body.name, appearance)
+                })
+                .collect(),
+        ))
+    }
+
+    /// Bodies missing from the scenario's description are drawn as small white spheres.
+    pub fn get(&self, name: &str) -> Appearance {
+        self.0.get(name).cloned().unwrap_or_default()
+    }
+}
+
+/// Bodies of a scenario file as far as drawing them goes.
+#[derive(Deserialize)]
+struct AppearanceFile {
+    bodies: Vec<BodyFile>,
+}
+
+#[derive(Deserialize)]
+struct BodyFile {
+    name: String,
+    radius: f32,
+    #[serde(default)]
+    texture: Option<String>,
+    #[serde(default = "default_color")]
+    color: [f32; 3], // srgb
+    #[serde(default)]
+    ring: Option<RingFile>,
+}
+
+#[derive(Deserialize)]
+struct RingFile {
+    inner_radius: f32,
+    outer_radius: f32,
+    color: [f32; 3],
+    opacity: f32,
+    #[serde(default)]
+    gaps: Vec<(f32, f32)>,
+    #[serde(default)]
+    faint: bool,
+}
+
+fn default_color() -> [f32; 3] {
+    [1.0; 3]
+}
+
+fn srgb([r, g, b]: [f32; 3]) -> Color {
+    Color::srgb(r, g, b)
+}
+
+fn planet_ring(name: &str) -> Option<RingConfig> {
+    match name {
+        "Saturn" => Some(RingConfig {
+            inner_radius: SATURN_RING_INNER,
+            outer_radius: SATURN_RING_OUTER,
+            color: Color::srgb(0.82, 0.74, 0.6),
+            opacity: 0.85,
+            gaps: vec![SATURN_CASSINI_DIVISION, SATURN_ENCKE_GAP],
+            faint: false,
+        }),
+        "Uranus" => Some(RingConfig {
+            inner_radius: URANUS_RING_INNER,
+            outer_radius: URANUS_RING_OUTER,
+            color: Color::srgb(0.5, 0.5, 0.5),
+            opacity: 0.25,
+            gaps: vec![],
+            faint: true,
+        }),
+        "Neptune" => Some(RingConfig {
+            inner_radius: NEPTUNE_RING_INNER,
+            outer_radius: NEPTUNE_RING_OUTER,
+            color: Color::srgb(0.45, 0.45, 0.5),
+            opacity: 0.15,
+            gaps: vec![],
+            faint: true,
+        }),
+        _ => None,
+    }
+}
@@ -1,41 +1,67 @@
 use std::f32::consts::{FRAC_PI_2, PI};
 
 use bevy::{
+    camera::Viewport,
     core_pipeline::{Skybox, tonemapping::Tonemapping},
-    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll},
+    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit},
     pbr::ScreenSpaceAmbientOcclusion,
     post_process::bloom::Bloom,
     prelude::*,
     render::render_resource::{TextureViewDescriptor, TextureViewDimension},
+    window::PrimaryWindow,
 };
 use bevy_egui::input::egui_wants_any_pointer_input;
 
+use crate::{
+    appearance::SUN_RADIUS,
+    planet::{Planet, Satellite},
+    scaling::DistanceScale,
+    sun::Sun,
+    ui::egui::{Gui, ViewportCorner},
+};
+
 const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
-const MIN_DISTANCE: f32 = 10.0;
-const MAX_DISTANCE: f32 = 1000.0;
+const MIN_DISTANCE_RADII: f32 = 3.0; // closest zoom, in radii of the focused body
+const MAX_DISTANCE_FACTOR: f32 = 2.5; // furthest zoom, relative to the outermost orbit
+const FAR_PLANE_FACTOR: f32 = 2.0; // far clipping plane, relative to the furthest zoom
+const NEAR_PLANE_FACTOR: f32 = 0.1; // near clipping plane, relative to the closest zoom
 
 const YAW_SENSITIVITY: f32 = 0.005;
 const PITCH_SENSITIVITY: f32 = 0.003;
-const ZOOM_SENSITIVITY: f32 = 2.0;
+// zoom is logarithmic, one scroll line changes the distance by this fraction
+const ZOOM_SENSITIVITY: f32 = 0.1;
+const PIXELS_PER_LINE: f32 = 20.0;
 
 pub const DEFAULT_DISTANCE: f32 = 50.0;
 // both values below are in degrees
 const DEFAULT_PITCH: f32 = 35.0;
 const DEFAULT_YAW: f32 = -90.0;
 
+const OVERVIEW_DISTANCE: f32 = 1000.0;
+const OVERVIEW_PITCH: f32 = 89.0; // degrees, looking (almost) straight down
+const VIEWPORT_MARGIN: u32 = 16; // physical pixels between the overview and window edges
+
 const SKYBOX_PATH: &str = "textures/skybox.png";
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_camera).add_systems(
-            Update,
-            (
-                camera_movement.run_if(not(egui_wants_any_pointer_input)),
-                setup_skybox,
-            ),
-        );
+        app.add_systems(Startup, setup_camera)
+            .add_systems(
+                Update,
+                (
+                    camera_movement.run_if(not(egui_wants_any_pointer_input)),
+                    apply_camera_focus,
+                    update_zoom_limits.after(camera_movement),
+                    update_overview_viewport,
+                    setup_skybox,
+                ),
+            )
+            .add_systems(
+                PostUpdate,
+                update_camera_transforms.before(TransformSystems::Propagate),
+            );
     }
 }
 
@@ -44,18 +70,40 @@ pub struct PointCamera {
     pub distance: f32, // or radius or zoom, however you call it
     pub pitch: f32,
     pub yaw: f32,
+    pub focus: Option<Entity>, // body the camera orbits around, `None` means the sun
+    pub min_distance: f32,
+    pub max_distance: f32,
 }
 
 impl PointCamera {
     pub fn new() -> Self {
         Self {
             distance: DEFAULT_DISTANCE,
-            pitch: DEFAULT_PITCH.to_radians() as f32,
-            yaw: DEFAULT_YAW.to_radians() as f32,
+            pitch: DEFAULT_PITCH.to_radians(),
+            yaw: DEFAULT_YAW.to_radians(),
+            focus: None,
+            min_distance: SUN_RADIUS * MIN_DISTANCE_RADII,
+            max_distance: OVERVIEW_DISTANCE,
+        }
+    }
+
+    pub fn overview() -> Self {
+        Self {
+            distance: OVERVIEW_DISTANCE,
+            pitch: OVERVIEW_PITCH.to_radians(),
+            ..Self::new()
         }
     }
 }
 
+/// The full-window camera.
+#[derive(Component)]
+pub struct MainCamera;
+
+/// Camera rendering into the small corner viewport.
+#[derive(Component)]
+pub struct OverviewCamera;
+
 #[derive(Resource)]
 struct Cubemap {
     is_loaded: bool,
@@ -73,6 +121,30 @@ fn setup_camera(mut cmds: Commands, assets: Res<AssetServer>) {
         Msaa::Off,
         ScreenSpaceAmbientOcclusion::default(),
         PointCamera::new(),
+        MainCamera,
+        IsDefaultUiCamera,
+        Skybox {
+            image: skybox_handle.clone(),
+            brightness: 500.0,
+            ..Default::default()
+        },
+    ));
+
+    // rendered on top of the main camera, its viewport is set every frame from the ui settings
+    cmds.spawn((
+        Camera3d::default(),
+        Camera {
+            order: 1,
+            is_active: false,
+            clear_color: ClearColorConfig::None,
+            ..Default::default()
+        },
+        Transform::default().looking_at(Vec3::ZERO, Vec3::Y),
+        Tonemapping::TonyMcMapface,
+        Bloom::NATURAL,
+        Msaa::Off,
+        PointCamera::overview(),
+        OverviewCamera,
         Skybox {
             image: skybox_handle.clone(),
             brightness: 500.0,
@@ -113,12 +185,30 @@ fn setup_skybox(
 }
 
 fn camera_movement(
-    query: Single<(&mut Transform, &mut PointCamera)>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &mut PointCamera)>,
     mouse_buttons: Res<ButtonInput<MouseButton>>,
     mouse_motion: Res<AccumulatedMouseMotion>,
     mouse_scroll: Res<AccumulatedMouseScroll>,
 ) {
-    let (mut transform, mut camera) = query.into_inner();
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    // only the top-most camera under the cursor is controlled
+    let Some((_, mut camera)) = cameras
+        .into_iter()
+        .filter(|(camera, _)| camera.is_active)
+        .filter(|(camera, _)| {
+            camera
+                .logical_viewport_rect()
+                .is_some_and(|rect| rect.contains(cursor))
+        })
+        .max_by_key(|(camera, _)| camera.order)
+    else {
+        return;
+    };
+
     let delta = mouse_motion.delta;
 
     let delta_yaw = delta.x * YAW_SENSITIVITY;
@@ -126,18 +216,144 @@ fn camera_movement(
 
     if mouse_buttons.pressed(MouseButton::Left) {
         camera.yaw += delta_yaw;
-        camera.yaw = camera.yaw % (2.0 * PI); // wrap yaw so that it doesnt overflow if we keep spinning
+        camera.yaw %= 2.0 * PI; // wrap yaw so that it doesnt overflow if we keep spinning
 
         camera.pitch += delta_pitch;
         camera.pitch = camera.pitch.clamp(-PITCH_LIMIT, PITCH_LIMIT);
     }
 
-    camera.distance -= mouse_scroll.delta.y * ZOOM_SENSITIVITY;
-    camera.distance = camera.distance.clamp(MIN_DISTANCE, MAX_DISTANCE);
+    let scroll = match mouse_scroll.unit {
+        MouseScrollUnit::Line => mouse_scroll.delta.y,
+        MouseScrollUnit::Pixel => mouse_scroll.delta.y / PIXELS_PER_LINE,
+    };
 
-    transform.translation.x = camera.distance * camera.pitch.cos() * camera.yaw.cos();
-    transform.translation.y = camera.distance * camera.pitch.sin();
-    transform.translation.z = camera.distance * camera.pitch.cos() * camera.yaw.sin();
+    // zooming in log space takes the same number of steps to go from a planet's surface to its
+    // neighbourhood as from the inner planets to the whole system
+    camera.distance *= (-scroll * ZOOM_SENSITIVITY).exp();
+    camera.distance = camera
+        .distance
+        .clamp(camera.min_distance, camera.max_distance);
+}
+
+fn update_zoom_limits(
+    scale: Res<DistanceScale>,
+    cameras: Query<(&mut PointCamera, &mut Projection)>,
+    planets: Query<(&Planet, &Transform, Has<Satellite>)>,
+    sun: Single<&Transform, With<Sun>>,
+) {
+    // moon orbits are tiny compared to the ones around the sun
+    let outermost_orbit = planets
+        .iter()
+        .filter(|(_, _, is_satellite)| !is_satellite)
+        .flat_map(|(planet, transform, _)| {
+            planet
+                .orbit_points
+                .iter()
+                .map(|p| scale.scale_distance_to_bevy(*p).length())
+                .chain([transform.translation.length()])
+        })
+        .fold(0.0, f32::max);
+
+    for (mut camera, mut projection) in cameras {
+        // meshes are scaled when bodies are rendered to scale
+        let (radius, target) = camera
+            .focus
+            .and_then(|entity| planets.get(entity).ok())
+            .map_or(
+                (SUN_RADIUS * sun.scale.x, Vec3::ZERO),
+                |(planet, transform, _)| {
+                    (
+                        planet.render_radius * transform.scale.x,
+                        transform.translation,
+                    )
+                },
+            );
+
+        camera.min_distance = radius * MIN_DISTANCE_RADII;
+        camera.max_distance =
+            ((outermost_orbit + target.length()) * MAX_DISTANCE_FACTOR).max(camera.min_distance);
+        camera.distance = camera
+            .distance
+            .clamp(camera.min_distance, camera.max_distance);
+
+        // keep the whole system between the clipping planes, without recomputing the projection
+        // each frame
+        let near = camera.min_distance * NEAR_PLANE_FACTOR;
+        let far = camera.max_distance * FAR_PLANE_FACTOR;
+        if let Projection::Perspective(perspective) = projection.bypass_change_detection()
+            && (perspective.near != near || perspective.far != far)
+        {
+            perspective.near = near;
+            perspective.far = far;
+            projection.set_changed();
+        }
+    }
+}
+
+fn apply_camera_focus(
+    settings: Res<Gui>,
+    mut main_camera: Single<&mut PointCamera, (With<MainCamera>, Without<OverviewCamera>)>,
+    mut overview_camera: Single<&mut PointCamera, (With<OverviewCamera>, Without<MainCamera>)>,
+) {
+    main_camera.focus = settings.main_focus;
+    overview_camera.focus = settings.overview.focus;
+}
+
+fn update_overview_viewport(
+    settings: Res<Gui>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<&mut Camera, With<OverviewCamera>>,
+) {
+    let mut camera = camera.into_inner();
+    let overview = &settings.overview;
 
-    transform.look_at(Vec3::ZERO, Vec3::Y);
+    if camera.is_active != overview.enabled {
+        camera.is_active = overview.enabled;
+    }
+
+    let window_size = window.physical_size();
+    let size = (window_size.as_vec2() * overview.size)
+        .as_uvec2()
+        .max(UVec2::ONE);
+    let far = window_size.saturating_sub(size + VIEWPORT_MARGIN);
+    let near = UVec2::splat(VIEWPORT_MARGIN).min(far);
+
+    let position = match overview.corner {
+        ViewportCorner::TopLeft => near,
+        ViewportCorner::TopRight => UVec2::new(far.x, near.y),
+        ViewportCorner::BottomLeft => UVec2::new(near.x, far.y),
+        ViewportCorner::BottomRight => far,
+    };
+
+    // avoid touching the camera every frame, changing it recomputes its projection
+    let unchanged = camera.viewport.as_ref().is_some_and(|viewport| {
+        viewport.physical_position == position && viewport.physical_size == size
+    });
+    if !unchanged {
+        camera.viewport = Some(Viewport {
+            physical_position: position,
+            physical_size: size,
+            ..Default::default()
+        });
+    }
+}
+
+fn update_camera_transforms(
+    cameras: Query<(&mut Transform, &PointCamera)>,
+    targets: Query<&Transform, Without<PointCamera>>,
+) {
+    for (mut transform, camera) in cameras {
+        let target = camera
+            .focus
+            .and_then(|entity| targets.get(entity).ok())
+            .map_or(Vec3::ZERO, |target| target.translation);
+
+        transform.translation.x =
+            target.x + camera.distance * camera.pitch.cos() * camera.yaw.cos();
+        transform.translation.y = target.y + camera.distance * camera.pitch.sin();
+        transform.translation.z =
+            target.z + camera.distance * camera.pitch.cos() * camera.yaw.sin();
+
+        transform.look_at(target, Vec3::Y);
+    }
 }
@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+
+use sun_orbit_simulation::math::{particles::ParticleState, physics::BodyState};
+
+use crate::{
+    collision::{Absorbed, restore_absorbed},
+    particle::TestParticle,
+    planet::{Planet, Satellite, SimulationTime, update_planet_physics},
+    precession::PrecessionMeter,
+    trail::Trail,
+};
+
+const CHECKPOINT_INTERVAL: f64 = 10.0 * 86_400.0; // simulated seconds
+const MAX_CHECKPOINTS: usize = 4096;
+// bodies and test particles over all checkpoints, about 128 MB, with many particles there are
+// fewer checkpoints
+const MAX_CHECKPOINT_STATES: usize = 1 << 22;
+
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Checkpoints>().add_systems(
+            FixedUpdate,
+            (
+                restore_checkpoint.before(update_planet_physics),
+                record_checkpoint.after(update_planet_physics),
+            ),
+        );
+    }
+}
+
+struct Checkpoint {
+    elapsed: f64,
+    bodies: Vec<BodySnapshot>,     // bodies absorbed by then are left out
+    particles: Vec<ParticleState>, // in the order they were spawned in
+}
+
+/// Everything about a body that changes while simulating, collisions included.
+struct BodySnapshot {
+    entity: Entity,
+    state: BodyState,
+    physical_radius: f64,
+    satellite: Option<Satellite>, // moons of an absorbed body move on to another parent
+}
+
+impl BodySnapshot {
+    fn restore(&self, planet: &mut Planet) {
+        planet.mass = self.state.mass;
+        planet.position = self.state.position;
+        planet.previous_position = self.state.position;
+        planet.velocity = self.state.velocity;
+        planet.physical_radius = self.physical_radius;
+    }
+}
+
+/// Snapshots of every body taken while the simulation runs, so it can be rewound.
+#[derive(Resource)]
+pub struct Checkpoints {
+    checkpoints: Vec<Checkpoint>, // sorted by time
+    interval: f64,                // grows every time old checkpoints get thinned out
+    restored: bool,               // the timeline branches off once the simulation moves on
+    catching_up: bool,            // still simulating from the checkpoint to the requested time
+    pub restore: Option<f64>,     // elapsed time requested by the timeline
+}
+
+impl Default for Checkpoints {
+    fn default() -> Self {
+        Self {
+            checkpoints: vec![],
+            interval: CHECKPOINT_INTERVAL,
+            restored: false,
+            catching_up: false,
+            restore: None,
+        }
+    }
+}
+
+impl Checkpoints {
+    /// Elapsed time of the first and the last checkpoint.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        Some((
+            self.checkpoints.first()?.elapsed,
+            self.checkpoints.last()?.elapsed,
+        ))
+    }
+
+    pub fn count(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    pub fn interval(&self) -> f64 {
+        self.interval
+    }
+}
+
+pub fn record_checkpoint(
+    sim_time: Res<SimulationTime>,
+    mut checkpoints: ResMut<Checkpoints>,
+    planets: Query<(Entity, &Planet, Option<&Satellite>)>,
+    particles: Query<(Entity, &TestParticle)>,
+) {
+    let elapsed = sim_time.elapsed;
+
+    // the old future is gone as soon as a rewound simulation moves in some direction, but not
+    // while it's getting to the requested time, so the timeline can still be dragged forward
+    let catching_up = checkpoints.catching_up;
+    checkpoints.catching_up = sim_time.target.is_some();
+    if checkpoints.restored && !catching_up && elapsed != sim_time.previous_elapsed {
+        checkpoints.restored = false;
+        let forward = elapsed > sim_time.previous_elapsed;
+        let origin = sim_time.previous_elapsed;
+        checkpoints.checkpoints.retain(|checkpoint| {
+            if forward {
+                checkpoint.elapsed <= origin
+            } else {
+                checkpoint.elapsed >= origin
+            }
+        });
+    }
+
+    let idx = checkpoints
+        .checkpoints
+        .partition_point(|checkpoint| checkpoint.elapsed < elapsed);
+    let too_close = [idx.checked_sub(1), Some(idx)]
+        .into_iter()
+        .flatten()
+        .filter_map(|i| checkpoints.checkpoints.get(i))
+        .any(|checkpoint| (checkpoint.elapsed - elapsed).abs() < checkpoints.interval);
+    if too_close {
+        return;
+    }
+
+    let bodies = planets
+        .iter()
+        .map(|(entity, planet, satellite)| BodySnapshot {
+            entity,
+            state: BodyState {
+                mass: planet.mass,
+                position: planet.position,
+                velocity: planet.velocity,
+            },
+            physical_radius: planet.physical_radius,
+            satellite: satellite.cloned(),
+        })
+        .collect::<Vec<_>>();
+    let particles = particles
+        .iter()
+        .sort_by_key::<Entity, _>(|entity| entity.index())
+        .map(|(_, particle)| particle.state())
+        .collect::<Vec<_>>();
+    let states = bodies.len() + particles.len();
+    checkpoints.checkpoints.insert(
+        idx,
+        Checkpoint {
+            elapsed,
+            bodies,
+            particles,
+        },
+    );
+
+    // keep the whole history, just at a coarser resolution, but at least the one checkpoint
+    let count = checkpoints.checkpoints.len();
+    if count > MAX_CHECKPOINTS || (count > 1 && count * states > MAX_CHECKPOINT_STATES) {
+        let mut keep = false;
+        checkpoints.checkpoints.retain(|_| {
+            keep = !keep;
+            keep
+        });
+        checkpoints.interval *= 2.0;
+    }
+}
+
+fn restore_checkpoint(
+    mut cmds: Commands,
+    mut checkpoints: ResMut<Checkpoints>,
+    (mut sim_time, mut meter): (ResMut<SimulationTime>, ResMut<PrecessionMeter>),
+    mut planets: Query<&mut Planet>,
+    absorbed: Query<&Absorbed>,
+    mut particles: Query<(Entity, &mut TestParticle)>,
+    mut trails: Query<&mut Trail>,
+) {
+    let Some(time) = checkpoints.restore.take() else {
+        return;
+    };
+
+    // the closest checkpoint before the requested time, the rest of the way is simulated
+    let idx = checkpoints
+        .checkpoints
+        .partition_point(|checkpoint| checkpoint.elapsed <= time);
+    let Some(checkpoint) = checkpoints.checkpoints.get(idx.saturating_sub(1)) else {
+        return;
+    };
+
+    for body in &checkpoint.bodies {
+        if let Ok(mut planet) = planets.get_mut(body.entity) {
+            body.restore(&mut planet);
+        } else if let Ok(Absorbed(planet)) = absorbed.get(body.entity) {
+            // collided since, so it comes back
+            let mut planet = planet.clone();
+            body.restore(&mut planet);
+            restore_absorbed(&mut cmds, body.entity, planet);
+        }
+
+        match &body.satellite {
+            Some(satellite) => cmds.entity(body.entity).insert(satellite.clone()),
+            None => cmds.entity(body.entity).remove::<Satellite>(),
+        };
+    }
+
+    let particles = particles
+        .iter_mut()
+        .sort_by_key::<Entity, _>(|entity| entity.index());
+    for ((_, mut particle), state) in particles.zip(&checkpoint.particles) {
+        *particle = TestParticle::new(*state);
+    }
+
+    sim_time.elapsed = checkpoint.elapsed;
+    sim_time.previous_elapsed = checkpoint.elapsed;
+    sim_time.target = (time != checkpoint.elapsed).then_some(time);
+    checkpoints.restored = true;
+    checkpoints.catching_up = sim_time.target.is_some();
+
+    // both would mix the abandoned timeline with the new one
+    for mut trail in &mut trails {
+        trail.clear();
+    }
+    meter.reset();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, math::DVec2};
+
+    use super::*;
+    use crate::collision::merge_collisions;
+
+    #[test]
+    fn rewinding_past_a_collision_brings_the_bodies_back() {
+        let mut world = World::new();
+        world.init_resource::<Checkpoints>();
+        world.init_resource::<PrecessionMeter>();
+        world.init_resource::<SimulationTime>();
+
+        // already touching, they merge on the first check
+        let heavy = Planet::bare(2e24, DVec2::ZERO, DVec2::ZERO);
+        let light = Planet::bare(1e24, DVec2::new(1e6, 0.0), DVec2::new(0.0, 1e3));
+        let survivor = world
+            .spawn((Name::new("Heavy"), heavy, Trail::default()))
+            .id();
+        let lost = world
+            .spawn((Name::new("Light"), light, Trail::default()))
+            .id();
+        let moon = world
+            .spawn((
+                Name::new("Moon"),
+                Planet::bare(1e20, DVec2::new(1e9, 0.0), DVec2::ZERO),
+                Satellite {
+                    parent: lost,
+                    innermost_distance: 1e9,
+                },
+                Trail::default(),
+            ))
+            .id();
+
+        world.run_system_once(record_checkpoint).unwrap();
+        world.run_system_once(merge_collisions).unwrap();
+        assert!(world.get::<Absorbed>(lost).is_some());
+        assert_eq!(world.get::<Planet>(survivor).unwrap().mass, 3e24);
+        assert_eq!(world.get::<Satellite>(moon).unwrap().parent, survivor);
+
+        world.resource_mut::<Checkpoints>().restore = Some(0.0);
+        world.run_system_once(restore_checkpoint).unwrap();
+        assert!(world.get::<Absorbed>(lost).is_none());
+        assert_eq!(world.get::<Planet>(lost).unwrap().mass, 1e24);
+        assert_eq!(world.get::<Planet>(survivor).unwrap().mass, 2e24);
+        assert_eq!(world.get::<Planet>(survivor).unwrap().physical_radius, 1e8);
+        assert_eq!(world.get::<Satellite>(moon).unwrap().parent, lost);
+    }
+
+    #[test]
+    fn rewinding_brings_the_test_particles_along() {
+        let mut world = World::new();
+        world.init_resource::<Checkpoints>();
+        world.init_resource::<PrecessionMeter>();
+        world.init_resource::<SimulationTime>();
+
+        let states = [1e11, 2e11].map(|distance| ParticleState {
+            position: DVec2::new(distance, 0.0),
+            velocity: DVec2::new(0.0, 3e4),
+        });
+        let particles = states.map(|state| world.spawn(TestParticle::new(state)).id());
+        world.run_system_once(record_checkpoint).unwrap();
+
+        for particle in particles {
+            let mut particle = world.get_mut::<TestParticle>(particle).unwrap();
+            particle.position *= 2.0;
+            particle.velocity = DVec2::ZERO;
+        }
+        world.resource_mut::<SimulationTime>().elapsed = CHECKPOINT_INTERVAL;
+
+        world.resource_mut::<Checkpoints>().restore = Some(0.0);
+        world.run_system_once(restore_checkpoint).unwrap();
+        for (particle, state) in particles.into_iter().zip(states) {
+            let particle = world.get::<TestParticle>(particle).unwrap();
+            assert_eq!(particle.position, state.position);
+            assert_eq!(particle.previous_position, state.position);
+            assert_eq!(particle.velocity, state.velocity);
+        }
+    }
+}
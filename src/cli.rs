@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use sun_orbit_simulation::math::{calendar::Date, physics::Integrator};
+
+/// N-body simulation of the solar system.
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Scenario to simulate instead of the built-in solar system, as JSON.
+    #[arg(long, value_name = "FILE")]
+    pub scenario: Option<PathBuf>,
+
+    /// Start from a previously saved simulation state. Headless runs only take the bodies, test
+    /// particles and time from it and go on for the duration from there.
+    #[arg(long, value_name = "FILE", conflicts_with = "start_date")]
+    pub load: Option<PathBuf>,
+
+    /// Initial conditions from JPL Horizons vector tables, saved as text or CSV. Bodies are
+    /// matched by name and the epoch is taken from the first file.
+    #[arg(long, value_name = "FILE", num_args = 1..)]
+    pub horizons: Vec<PathBuf>,
+
+    /// Add this many massless asteroids to the main belt, between 2 and 3.5 AU from the sun.
+    #[arg(long, value_name = "COUNT", default_value_t = 0)]
+    pub asteroids: usize,
+
+    /// Add this many massless asteroids around Jupiter's L4 and L5 points.
+    #[arg(long, value_name = "COUNT", default_value_t = 0)]
+    pub trojans: usize,
+
+    /// Date to start at, as "YYYY-MM-DD [HH:MM[:SS]]". The bodies are simulated there from the
+    /// scenario's epoch, so far away dates take a while.
+    #[arg(long, value_name = "DATE", value_parser = parse_date)]
+    pub start_date: Option<Date>,
+
+    /// Simulated seconds per real second.
+    #[arg(long, value_name = "RATE")]
+    pub time_rate: Option<f64>,
+
+    /// Deterministic mode: every physics tick advances exactly this many simulated seconds,
+    /// regardless of frame rate and time rate, so runs can be repeated bit for bit.
+    #[arg(long, value_name = "SECONDS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub fixed_step: Option<u32>,
+
+    /// Integrator for the bodies: verlet, yoshida or rk4.
+    #[arg(long, value_name = "NAME", default_value = "verlet")]
+    pub integrator: Integrator,
+
+    /// Plummer softening length in meters: gravity between bodies closer than about this is
+    /// weakened, so close passes don't fling them out.
+    #[arg(long, value_name = "METERS", default_value_t = 0.0)]
+    pub softening: f64,
+
+    /// Take shorter steps while two bodies are close to each other.
+    #[arg(long)]
+    pub close_encounters: bool,
+
+    /// Sum up gravity with a Barnes-Hut tree instead of over every pair of bodies, with this
+    /// opening angle in radians. Faster for thousands of bodies, but approximate.
+    #[arg(long, value_name = "ANGLE", num_args = 0..=1, default_missing_value = "0.5")]
+    pub barnes_hut: Option<f64>,
+
+    /// Window width in pixels.
+    #[arg(long, value_name = "PIXELS", default_value_t = 1920)]
+    pub width: u32,
+
+    /// Window height in pixels.
+    #[arg(long, value_name = "PIXELS", default_value_t = 1080)]
+    pub height: u32,
+
+    /// Cover the whole primary monitor.
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Run without a window, simulating as fast as possible and exiting when done.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Simulated days to run for in headless mode, counted from the start date.
+    #[arg(
+        long,
+        value_name = "DAYS",
+        default_value_t = 365.25,
+        requires = "headless"
+    )]
+    pub duration: f64,
+
+    /// Write the final state of every body here in headless mode, CSV for `.csv` files and
+    /// JSON Lines otherwise.
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    pub output: Option<PathBuf>,
+
+    /// Record the state of every body to a file, CSV for `.csv` files and JSON Lines otherwise.
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Simulated days between recorded samples.
+    #[arg(long, value_name = "DAYS", default_value_t = 1.0, value_parser = parse_interval)]
+    pub record_interval: f64,
+}
+
+fn parse_date(text: &str) -> Result<Date, String> {
+    Date::parse(text).ok_or_else(|| format!("expected YYYY-MM-DD [HH:MM[:SS]], got {text}"))
+}
+
+fn parse_interval(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(days) if days > 0.0 && days.is_finite() => Ok(days),
+        Ok(_) => Err(format!("expected more than 0 days, got {text}")),
+        Err(err) => Err(err.to_string()),
+    }
+}
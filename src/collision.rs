@@ -0,0 +1,241 @@
+use bevy::{math::DVec2, prelude::*};
+use sun_orbit_simulation::math::{
+    calendar::Date,
+    collision::{merge, merged_radius, possible_contacts, touched},
+    physics::BodyState,
+};
+
+use crate::{
+    checkpoint::record_checkpoint,
+    planet::{Planet, Satellite, SimulationTime, update_planet_physics},
+    sun::Sun,
+};
+
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            merge_collisions
+                .after(update_planet_physics)
+                .before(record_checkpoint),
+        );
+    }
+}
+
+/// A body that merged into another one. It's hidden and left out of the physics instead of
+/// despawned, so rewinding to before the collision brings it back.
+#[derive(Component)]
+pub struct Absorbed(pub Planet);
+
+/// Takes a body out of the simulation, `planet` is how it was when it merged.
+pub fn absorb(cmds: &mut Commands, entity: Entity, planet: Planet) {
+    cmds.entity(entity)
+        .remove::<(Planet, Satellite)>()
+        .insert((Absorbed(planet), Visibility::Hidden));
+}
+
+/// Brings an absorbed body back as `planet`.
+pub fn restore_absorbed(cmds: &mut Commands, entity: Entity, planet: Planet) {
+    cmds.entity(entity)
+        .remove::<Absorbed>()
+        .insert((planet, Visibility::Inherited));
+}
+
+/// Merges bodies that touched during the last tick. The sun or else the heavier body survives,
+/// keeping its name, and moons of the absorbed one move over to it.
+pub fn merge_collisions(
+    mut cmds: Commands,
+    sim_time: Res<SimulationTime>,
+    mut planets: Query<(Entity, &Name, &mut Planet, Has<Sun>)>,
+    mut satellites: Query<(Entity, &mut Satellite)>,
+) {
+    let dt = sim_time.elapsed - sim_time.previous_elapsed;
+    let mut bodies = planets
+        .iter()
+        .map(|(entity, _, planet, is_sun)| Body {
+            entity,
+            is_sun,
+            state: BodyState {
+                mass: planet.mass,
+                position: planet.position,
+                velocity: planet.velocity,
+            },
+            previous_position: planet.previous_position,
+            radius: planet.physical_radius,
+        })
+        .collect::<Vec<_>>();
+
+    // only bodies whose paths came close are checked, the pairs are resolved one at a time and a
+    // body that was already absorbed is out of the game. A merged body is still paired with what
+    // the surviving one was close to, anything else it reaches gets merged on the next tick.
+    let states = bodies.iter().map(|body| body.state).collect::<Vec<_>>();
+    let previous = bodies
+        .iter()
+        .map(|body| body.previous_position)
+        .collect::<Vec<_>>();
+    let radii = bodies.iter().map(|body| body.radius).collect::<Vec<_>>();
+    let mut absorbed: Vec<(Entity, Entity)> = Vec::new();
+    for (i, j) in possible_contacts(&states, &previous, &radii) {
+        let (a, b) = (&bodies[i], &bodies[j]);
+        if absorbed
+            .iter()
+            .any(|(entity, _)| *entity == a.entity || *entity == b.entity)
+        {
+            continue;
+        }
+
+        let reach = a.radius + b.radius;
+        if !touched(
+            &a.state,
+            a.previous_position,
+            &b.state,
+            b.previous_position,
+            reach,
+            dt,
+        ) {
+            continue;
+        }
+
+        let (survivor, lost) = if a.is_sun || (!b.is_sun && a.state.mass >= b.state.mass) {
+            (i, j)
+        } else {
+            (j, i)
+        };
+
+        let name = |body: &Body| planets.get(body.entity).map_or("?", |(_, name, ..)| name);
+        info!(
+            "{} collided with {} on {} and merged into it",
+            name(&bodies[lost]),
+            name(&bodies[survivor]),
+            Date::from_julian_date(sim_time.julian_date())
+        );
+
+        bodies[survivor] = bodies[survivor].merge(&bodies[lost]);
+        absorbed.push((bodies[lost].entity, bodies[survivor].entity));
+    }
+
+    if absorbed.is_empty() {
+        return;
+    }
+
+    for body in &bodies {
+        if let Ok((_, _, mut planet, _)) = planets.get_mut(body.entity) {
+            planet.mass = body.state.mass;
+            planet.position = body.state.position;
+            planet.previous_position = body.previous_position;
+            planet.velocity = body.state.velocity;
+            planet.physical_radius = body.radius;
+        }
+    }
+
+    for (entity, _) in &absorbed {
+        if let Ok((_, _, planet, _)) = planets.get(*entity) {
+            absorb(&mut cmds, *entity, planet.clone());
+        }
+    }
+
+    // moons of an absorbed body orbit whatever it merged into, or become planets of the sun
+    for (entity, mut satellite) in &mut satellites {
+        let Some(&(_, survivor)) = absorbed.iter().find(|(e, _)| *e == satellite.parent) else {
+            continue;
+        };
+        if planets.get(survivor).is_ok_and(|(.., is_sun)| is_sun) {
+            cmds.entity(entity).remove::<Satellite>();
+        } else {
+            satellite.parent = survivor;
+        }
+    }
+}
+
+struct Body {
+    entity: Entity,
+    is_sun: bool,
+    state: BodyState,
+    previous_position: DVec2, // where the tick started
+    radius: f64,
+}
+
+impl Body {
+    fn merge(&self, other: &Body) -> Body {
+        let previous = |body: &Body| BodyState {
+            position: body.previous_position,
+            ..body.state
+        };
+
+        Body {
+            entity: self.entity,
+            is_sun: self.is_sun,
+            state: merge(&self.state, &other.state),
+            previous_position: merge(&previous(self), &previous(other)).position,
+            radius: merged_radius(self.radius, other.radius),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use sun_orbit_simulation::constants::SUN_MASS;
+
+    use super::*;
+
+    fn totals(world: &mut World) -> (f64, DVec2) {
+        let mut planets = world.query::<&Planet>();
+        planets
+            .iter(world)
+            .fold((0.0, DVec2::ZERO), |(mass, momentum), p| {
+                (mass + p.mass, momentum + p.mass * p.velocity)
+            })
+    }
+
+    #[test]
+    fn the_sun_survives_and_nothing_is_lost() {
+        let mut world = World::new();
+        world.init_resource::<SimulationTime>();
+
+        // heavier than the sun and touching it, another pair touching elsewhere
+        let sun = world
+            .spawn((
+                Name::new("Sun"),
+                Planet::bare(SUN_MASS, DVec2::ZERO, DVec2::ZERO),
+                Sun,
+            ))
+            .id();
+        let star = world
+            .spawn((
+                Name::new("Star"),
+                Planet::bare(2.0 * SUN_MASS, DVec2::new(1e8, 0.0), DVec2::new(-2e4, 1e3)),
+            ))
+            .id();
+        let a = world
+            .spawn((
+                Name::new("A"),
+                Planet::bare(1e24, DVec2::new(1e12, 0.0), DVec2::new(0.0, 1e4)),
+            ))
+            .id();
+        let b = world
+            .spawn((
+                Name::new("B"),
+                Planet::bare(3e24, DVec2::new(1e12, 1e8), DVec2::new(5e3, 0.0)),
+            ))
+            .id();
+
+        let (mass, momentum) = totals(&mut world);
+        world.run_system_once(merge_collisions).unwrap();
+
+        assert!(world.get::<Planet>(sun).is_some());
+        assert!(world.get::<Absorbed>(star).is_some());
+        assert!(world.get::<Absorbed>(a).is_some());
+        assert!(world.get::<Planet>(b).is_some());
+
+        let (merged_mass, merged_momentum) = totals(&mut world);
+        assert_eq!(merged_mass, mass);
+        let error = (merged_momentum - momentum).length();
+        assert!(
+            error <= 1e-12 * momentum.length(),
+            "momentum off by {error}"
+        );
+    }
+}
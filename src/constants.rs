@@ -1,4 +1,6 @@
 pub const G: f64 = 6.6743e-11;
+pub const C: f64 = 299_792_458.0; // speed of light, m/s
+pub const AU: f64 = 1.495_978_707e11; // astronomical unit, m
 pub const SUN_MASS: f64 = 1.9885e30;
 
 pub const MERCURY_MASS: f64 = 3.3011e23;
@@ -33,15 +35,78 @@ pub const NEPTUNE_MASS: f64 = 1.02409e26;
 pub const NEPTUNE_POS_X: f64 = 4.5e12;
 pub const NEPTUNE_VEL_Y: f64 = 5_450.0;
 
+// moons, positions and velocities are relative to their parent planet
+pub const MOON_MASS: f64 = 7.342e22;
+pub const MOON_POS_X: f64 = 3.844e8;
+pub const MOON_VEL_Y: f64 = 1_022.0;
+
+pub const IO_MASS: f64 = 8.9319e22;
+pub const IO_POS_X: f64 = 4.217e8;
+pub const IO_VEL_Y: f64 = 17_334.0;
+
+pub const EUROPA_MASS: f64 = 4.7998e22;
+pub const EUROPA_POS_X: f64 = 6.709e8;
+pub const EUROPA_VEL_Y: f64 = 13_740.0;
+
+pub const GANYMEDE_MASS: f64 = 1.4819e23;
+pub const GANYMEDE_POS_X: f64 = 1.0704e9;
+pub const GANYMEDE_VEL_Y: f64 = 10_880.0;
+
+pub const CALLISTO_MASS: f64 = 1.0759e23;
+pub const CALLISTO_POS_X: f64 = 1.8827e9;
+pub const CALLISTO_VEL_Y: f64 = 8_204.0;
+
+pub const TITAN_MASS: f64 = 1.3452e23;
+pub const TITAN_POS_X: f64 = 1.22187e9;
+pub const TITAN_VEL_Y: f64 = 5_570.0;
+
 pub const DISTANCE_SCALE: f64 = 0.5e10;
+// every moon system is scaled on its own, the innermost moon is placed this many rendered radii
+// away from its parent and distances further out are compressed by this power
+pub const INNERMOST_SATELLITE_RADII: f64 = 3.0;
+pub const SATELLITE_COMPRESSION: f64 = 0.5;
+
+// axial tilt relative to the orbital plane (in degrees) and sidereal rotation period (in seconds),
+// retrograde rotation is expressed by a tilt above 90 degrees
+pub const SUN_OBLIQUITY: f64 = 7.25;
+pub const SUN_ROTATION_PERIOD: f64 = 2_192_832.0;
+pub const MERCURY_OBLIQUITY: f64 = 0.034;
+pub const MERCURY_ROTATION_PERIOD: f64 = 5_067_014.0;
+pub const VENUS_OBLIQUITY: f64 = 177.36;
+pub const VENUS_ROTATION_PERIOD: f64 = 20_997_360.0;
+pub const EARTH_OBLIQUITY: f64 = 23.44;
+pub const EARTH_ROTATION_PERIOD: f64 = 86_164.1;
+pub const MARS_OBLIQUITY: f64 = 25.19;
+pub const MARS_ROTATION_PERIOD: f64 = 88_642.7;
+pub const JUPITER_OBLIQUITY: f64 = 3.13;
+pub const JUPITER_ROTATION_PERIOD: f64 = 35_730.0;
+pub const SATURN_OBLIQUITY: f64 = 26.73;
+pub const SATURN_ROTATION_PERIOD: f64 = 38_018.0;
+pub const URANUS_OBLIQUITY: f64 = 97.77;
+pub const URANUS_ROTATION_PERIOD: f64 = 62_064.0;
+pub const NEPTUNE_OBLIQUITY: f64 = 28.32;
+pub const NEPTUNE_ROTATION_PERIOD: f64 = 57_996.0;
+// the moons are tidally locked, so they rotate once per orbit
+pub const MOON_ROTATION_PERIOD: f64 = 2_360_591.0;
+pub const IO_ROTATION_PERIOD: f64 = 152_854.0;
+pub const EUROPA_ROTATION_PERIOD: f64 = 306_822.0;
+pub const GANYMEDE_ROTATION_PERIOD: f64 = 618_153.0;
+pub const CALLISTO_ROTATION_PERIOD: f64 = 1_441_931.0;
+pub const TITAN_ROTATION_PERIOD: f64 = 1_377_648.0;
 
-// values for rendering, they're not used for physics
-pub const SUN_RADIUS: f32 = 3.0;
-pub const EARTH_RADIUS: f32 = 1.5;
-pub const MERCURY_RADIUS: f32 = EARTH_RADIUS * 0.3829;
-pub const VENUS_RADIUS: f32 = EARTH_RADIUS * 0.9499;
-pub const MARS_RADIUS: f32 = EARTH_RADIUS * 0.533;
-pub const JUPITER_RADIUS: f32 = 5.0;
-pub const SATURN_RADIUS: f32 = 3.5;
-pub const URANUS_RADIUS: f32 = 3.0;
-pub const NEPTUNE_RADIUS: f32 = 2.5;
+// mean radii (in meters), used when rendering bodies to scale
+pub const SUN_MEAN_RADIUS: f64 = 6.957e8;
+pub const MERCURY_MEAN_RADIUS: f64 = 2.4397e6;
+pub const VENUS_MEAN_RADIUS: f64 = 6.0518e6;
+pub const EARTH_MEAN_RADIUS: f64 = 6.371e6;
+pub const MARS_MEAN_RADIUS: f64 = 3.3895e6;
+pub const JUPITER_MEAN_RADIUS: f64 = 6.9911e7;
+pub const SATURN_MEAN_RADIUS: f64 = 5.8232e7;
+pub const URANUS_MEAN_RADIUS: f64 = 2.5362e7;
+pub const NEPTUNE_MEAN_RADIUS: f64 = 2.4622e7;
+pub const MOON_MEAN_RADIUS: f64 = 1.7374e6;
+pub const IO_MEAN_RADIUS: f64 = 1.8216e6;
+pub const EUROPA_MEAN_RADIUS: f64 = 1.5608e6;
+pub const GANYMEDE_MEAN_RADIUS: f64 = 2.6341e6;
+pub const CALLISTO_MEAN_RADIUS: f64 = 2.4103e6;
+pub const TITAN_MEAN_RADIUS: f64 = 2.5747e6;
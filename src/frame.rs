@@ -0,0 +1,160 @@
+use bevy::{math::DVec2, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    planet::{Planet, Satellite},
+    sun::Sun,
+    ui::egui::Gui,
+};
+
+pub struct FramePlugin;
+
+impl Plugin for FramePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RenderFrame>()
+            .add_systems(Update, update_render_frame);
+    }
+}
+
+/// Frame everything is rendered in, physics always runs in the same inertial frame.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReferenceFrame {
+    Heliocentric,
+    Barycentric,
+    BodyCentric, // centered on the selected body
+    CoRotating, // centered on the barycenter of the selected body and its primary, rotating with them
+}
+
+impl ReferenceFrame {
+    pub const ALL: [Self; 4] = [
+        Self::Heliocentric,
+        Self::Barycentric,
+        Self::BodyCentric,
+        Self::CoRotating,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Heliocentric => "Heliocentric",
+            Self::Barycentric => "Barycentric",
+            Self::BodyCentric => "Body-centric",
+            Self::CoRotating => "Co-rotating",
+        }
+    }
+
+    pub fn needs_body(&self) -> bool {
+        matches!(self, Self::BodyCentric | Self::CoRotating)
+    }
+
+    /// Transform from inertial coordinates into this frame at the interpolated moment `alpha`
+    /// between the last two physics steps. Frames needing a body fall back to heliocentric
+    /// without one.
+    pub fn transform(
+        self,
+        body: Option<Entity>,
+        bodies: &FrameBodies,
+        alpha: f64,
+    ) -> FrameTransform {
+        let position = |planet: &Planet| planet.previous_position.lerp(planet.position, alpha);
+
+        let sun = bodies
+            .iter()
+            .find(|(_, _, is_sun)| *is_sun)
+            .map(|(planet, ..)| planet);
+        let heliocentric = FrameTransform::centered(sun.map_or(DVec2::ZERO, position));
+        let target = body.and_then(|entity| bodies.get(entity).ok());
+
+        match (self, target) {
+            (Self::Heliocentric, _) | (Self::BodyCentric | Self::CoRotating, None) => heliocentric,
+            (Self::Barycentric, _) => {
+                let (weighted, mass) =
+                    bodies
+                        .iter()
+                        .fold((DVec2::ZERO, 0.0), |(weighted, mass), (planet, ..)| {
+                            (
+                                weighted + planet.mass * position(planet),
+                                mass + planet.mass,
+                            )
+                        });
+                if mass > 0.0 {
+                    FrameTransform::centered(weighted / mass)
+                } else {
+                    heliocentric
+                }
+            }
+            (Self::BodyCentric, Some((planet, ..))) => FrameTransform::centered(position(planet)),
+            (Self::CoRotating, Some((planet, satellite, _))) => {
+                // moons co-rotate with their parent, everything else with the sun
+                let Some(primary) = satellite
+                    .and_then(|s| bodies.get(s.parent).ok())
+                    .map(|(primary, ..)| primary)
+                    .or(sun)
+                else {
+                    return heliocentric;
+                };
+
+                let primary_position = position(primary);
+                let body_position = position(planet);
+                let origin = (primary.mass * primary_position + planet.mass * body_position)
+                    / (primary.mass + planet.mass);
+
+                // the body always lies on the +x axis
+                FrameTransform {
+                    origin,
+                    rotation: DVec2::from_angle(-(body_position - primary_position).to_angle()),
+                }
+            }
+        }
+    }
+}
+
+pub type FrameBodies<'w, 's> =
+    Query<'w, 's, (&'static Planet, Option<&'static Satellite>, Has<Sun>)>;
+
+/// Maps inertial positions in meters into a reference frame, still in meters.
+#[derive(Clone, Copy)]
+pub struct FrameTransform {
+    pub origin: DVec2,
+    pub rotation: DVec2, // unit vector undoing the frame's own rotation
+}
+
+impl Default for FrameTransform {
+    fn default() -> Self {
+        Self::centered(DVec2::ZERO)
+    }
+}
+
+impl FrameTransform {
+    pub fn centered(origin: DVec2) -> Self {
+        Self {
+            origin,
+            rotation: DVec2::X,
+        }
+    }
+
+    pub fn apply(&self, position: DVec2) -> DVec2 {
+        self.rotation.rotate(position - self.origin)
+    }
+
+    /// For relative positions, which only need to be rotated.
+    pub fn apply_vector(&self, vector: DVec2) -> DVec2 {
+        self.rotation.rotate(vector)
+    }
+}
+
+/// Frame transform for the current rendered frame.
+#[derive(Resource, Default)]
+pub struct RenderFrame(pub FrameTransform);
+
+pub fn update_render_frame(
+    settings: Res<Gui>,
+    fixed_time: Res<Time<Fixed>>,
+    bodies: FrameBodies,
+    mut frame: ResMut<RenderFrame>,
+) {
+    frame.0 = settings.reference_frame.transform(
+        settings.frame_body,
+        &bodies,
+        fixed_time.overstep_fraction_f64(),
+    );
+}
@@ -0,0 +1,293 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use sun_orbit_simulation::math::{
+    calendar::Date,
+    physics::{BodyState, ForceModel, Integrator},
+};
+
+use crate::{
+    collision::merge_collisions,
+    particle::{TestParticle, TestParticles},
+    planet::{
+        InitialScenario, PhysicsBodies, Planet, Satellite, SimulationTime, advance_bodies,
+        orbited_bodies,
+    },
+    recorder::Recorder,
+    save::{LoadingBodies, SaveFile, apply_simulation},
+    sun::{Sun, balance_momentum, sun_body},
+};
+
+// simulated seconds per update unless a fixed step is set, collisions are checked after each
+const HEADLESS_TICK: f64 = 86_400.0;
+const LOG_INTERVAL: f64 = 365.25 * 86_400.0; // simulated seconds between progress messages
+
+/// Runs the physics without a window or rendering, as fast as possible, then exits.
+pub struct HeadlessPlugin;
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationTime>()
+            .add_systems(Startup, spawn_bodies)
+            .add_systems(
+                PostStartup,
+                (balance_momentum, skip_to_start, load_save).chain(),
+            )
+            .add_systems(
+                Update,
+                (run_simulation, merge_collisions, finish_run).chain(),
+            );
+    }
+}
+
+/// What a headless run simulates and where it writes the result.
+#[derive(Resource)]
+pub struct HeadlessRun {
+    pub end: f64, // elapsed simulated seconds to stop at
+    pub forces: ForceModel,
+    pub integrator: Integrator,
+    pub output: Option<PathBuf>, // final state of every body, in the recorder's format
+    pub load: Option<SaveFile>,  // continued from instead of the scenario's initial state
+    pub finished: bool,
+}
+
+fn spawn_bodies(
+    mut cmds: Commands,
+    scenario: Res<InitialScenario>,
+    mut sim_time: ResMut<SimulationTime>,
+) {
+    sim_time.epoch = scenario.epoch;
+
+    cmds.spawn((Name::new("Sun"), sun_body(), Sun));
+
+    let mut spawned = Vec::new();
+    for body in scenario.initial_bodies() {
+        let mut planet = cmds.spawn((
+            Name::new(body.config.name.clone()),
+            // nothing is drawn, so the bodies have no render radius
+            Planet::new(body.config, 0.0, body.position, body.velocity),
+        ));
+
+        if let Some((parent, innermost_distance)) = body.satellite {
+            planet.insert(Satellite {
+                parent: spawned[parent],
+                innermost_distance,
+            });
+        }
+
+        spawned.push(planet.id());
+    }
+
+    cmds.spawn_batch(
+        scenario
+            .particles
+            .iter()
+            .map(|state| TestParticle::new(*state))
+            .collect::<Vec<_>>(),
+    );
+}
+
+// the run starts at the date the clock is set to jump to, nothing is recorded on the way there
+fn skip_to_start(
+    run: Res<HeadlessRun>,
+    mut sim_time: ResMut<SimulationTime>,
+    mut planets: PhysicsBodies,
+    mut particles: TestParticles,
+) {
+    if let Some(target) = sim_time.target.take() {
+        let dt = target - sim_time.elapsed;
+        let mut recorder = Recorder::default();
+        advance_bodies(
+            dt,
+            run.forces,
+            run.integrator,
+            &mut sim_time,
+            &mut recorder,
+            &mut planets,
+            &mut particles,
+        );
+    }
+}
+
+// only the bodies, test particles and time are taken from the save, the forces and the integrator
+// are the command line's, and the run lasts as long from the saved time on
+fn load_save(
+    mut cmds: Commands,
+    mut run: ResMut<HeadlessRun>,
+    mut sim_time: ResMut<SimulationTime>,
+    mut bodies: LoadingBodies,
+    mut particles: Query<(Entity, &mut TestParticle)>,
+) {
+    let Some(save) = run.load.take() else {
+        return;
+    };
+
+    let start = sim_time.elapsed;
+    apply_simulation(&mut cmds, &save, &mut sim_time, &mut bodies, &mut particles);
+    run.end += sim_time.elapsed - start;
+    info!(
+        "Continuing from {}",
+        Date::from_julian_date(sim_time.julian_date())
+    );
+}
+
+fn run_simulation(
+    mut run: ResMut<HeadlessRun>,
+    mut sim_time: ResMut<SimulationTime>,
+    mut recorder: ResMut<Recorder>,
+    mut planets: PhysicsBodies,
+    mut particles: TestParticles,
+) {
+    // in deterministic mode time moves in whole steps, only the very last one can be shorter
+    let remaining = run.end - sim_time.elapsed;
+    let step = sim_time.fixed_step.unwrap_or(HEADLESS_TICK);
+    let dt = remaining.clamp(-step, step);
+    advance_bodies(
+        dt,
+        run.forces,
+        run.integrator,
+        &mut sim_time,
+        &mut recorder,
+        &mut planets,
+        &mut particles,
+    );
+
+    run.finished = dt == remaining;
+    let period = |elapsed: f64| (elapsed / LOG_INTERVAL).floor();
+    if run.finished || period(sim_time.elapsed) != period(sim_time.previous_elapsed) {
+        info!(
+            "Simulated up to {}",
+            Date::from_julian_date(sim_time.julian_date())
+        );
+    }
+}
+
+// after the last tick's collisions are resolved, so the output doesn't have merged bodies twice
+fn finish_run(
+    run: Res<HeadlessRun>,
+    sim_time: Res<SimulationTime>,
+    mut recorder: ResMut<Recorder>,
+    planets: PhysicsBodies,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if !run.finished {
+        return;
+    }
+
+    recorder.stop();
+    if let Some(path) = &run.output {
+        let states = planets
+            .iter()
+            .sort_by_key::<Entity, _>(|entity| entity.index())
+            .map(|(_, _, planet, ..)| BodyState {
+                mass: planet.mass,
+                position: planet.position,
+                velocity: planet.velocity,
+            })
+            .collect::<Vec<_>>();
+
+        let mut output = Recorder::default();
+        if let Err(err) = output.start(path.clone()) {
+            error!("Couldn't write {}: {err}", path.display());
+            exit.write(AppExit::error());
+            return;
+        }
+        output.record(
+            sim_time.julian_date(),
+            sim_time.elapsed,
+            &orbited_bodies(&planets),
+            &states,
+        );
+        // a failed write stops the recording
+        if !output.is_recording() {
+            exit.write(AppExit::error());
+            return;
+        }
+        output.stop();
+        info!("Wrote final states to {}", path.display());
+    }
+
+    exit.write(AppExit::Success);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use sun_orbit_simulation::{scenario::Scenario, simulation::Simulation};
+
+    use super::*;
+
+    const STEP: f64 = 86_400.0; // longer than a substep, both have to split it the same way
+    const STEPS: u64 = 60;
+
+    fn assert_matches_library(recorder: Recorder) {
+        let mut scenario = Scenario::solar_system();
+        scenario.add_asteroid_belt(100);
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, HeadlessPlugin))
+            .insert_resource(InitialScenario(scenario.clone()))
+            .insert_resource(recorder)
+            .insert_resource(SimulationTime {
+                fixed_step: Some(STEP),
+                ..Default::default()
+            })
+            .insert_resource(HeadlessRun {
+                end: STEPS as f64 * STEP,
+                forces: ForceModel::default(),
+                integrator: Integrator::default(),
+                output: None,
+                load: None,
+                finished: false,
+            });
+        while !app.world().resource::<HeadlessRun>().finished {
+            app.update();
+        }
+
+        let mut simulation = Simulation::new(&scenario, STEP);
+        simulation.advance(STEPS);
+
+        let world = app.world_mut();
+        assert_eq!(
+            world.resource::<SimulationTime>().elapsed,
+            simulation.elapsed()
+        );
+
+        let mut planets = world.query::<(&Name, &Planet)>();
+        for (name, planet) in planets.iter(world) {
+            let i = simulation.names.iter().position(|n| n == name.as_str());
+            let body = simulation.bodies[i.unwrap()];
+            assert_eq!(planet.position, body.position, "{name} moved differently");
+            assert_eq!(planet.velocity, body.velocity, "{name} moved differently");
+        }
+
+        let mut particles = world.query::<&TestParticle>();
+        let particles = particles.iter(world).map(|p| p.state()).collect::<Vec<_>>();
+        assert_eq!(particles.len(), simulation.particles.len());
+        for (particle, expected) in particles.iter().zip(&simulation.particles) {
+            assert_eq!(particle.position, expected.position);
+            assert_eq!(particle.velocity, expected.velocity);
+        }
+    }
+
+    #[test]
+    fn fixed_step_runs_match_the_library() {
+        assert_matches_library(Recorder::default());
+    }
+
+    #[test]
+    fn recording_doesnt_change_the_run() {
+        // samples fall in the middle of the steps
+        let path = env::temp_dir().join(format!("headless-record-{}.csv", process::id()));
+        let mut recorder = Recorder::default();
+        recorder.interval = STEP / 3.0 + 1.0;
+        recorder.start(path.clone()).unwrap();
+        assert_matches_library(recorder);
+
+        let samples = fs::read_to_string(&path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert!(samples.lines().count() > STEPS as usize);
+    }
+}
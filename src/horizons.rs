@@ -0,0 +1,280 @@
+use std::{collections::HashMap, error::Error, fs, path::Path};
+
+use bevy_math::{DVec2, DVec3};
+
+use crate::{constants::AU, math::calendar::SECONDS_PER_DAY, scenario::Scenario};
+
+const MAX_EPOCH_DIFFERENCE: f64 = 1.0 / (24.0 * 60.0); // a minute, in days
+
+/// Vectors of one target body, relative to a center body.
+pub struct VectorTable {
+    pub target: String,
+    pub center: String,
+    pub records: Vec<VectorRecord>,
+}
+
+#[derive(Clone, Copy)]
+pub struct VectorRecord {
+    pub julian_date: f64,
+    pub position: DVec3, // m
+    pub velocity: DVec3, // m/s
+}
+
+pub fn read_vector_table(path: &Path) -> Result<VectorTable, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    parse_vector_table(&text).map_err(|err| format!("{}: {err}", path.display()).into())
+}
+
+/// Parses a vector table exported from JPL Horizons, either as plain text or with
+/// `CSV_FORMAT=YES`. Only the state vectors are used, light-time and range columns are ignored.
+pub fn parse_vector_table(text: &str) -> Result<VectorTable, Box<dyn Error>> {
+    let (header, rest) = text
+        .split_once("$$SOE")
+        .ok_or("missing $$SOE, not a Horizons table")?;
+    let (data, _) = rest.split_once("$$EOE").ok_or("missing $$EOE")?;
+
+    let target = header_value(header, "Target body name").ok_or("missing target body name")?;
+    let center = header_value(header, "Center body name").ok_or("missing center body name")?;
+
+    // kilometers and seconds unless stated otherwise
+    let units = header_value(header, "Output units").unwrap_or_default();
+    let (length, time) = match units.as_str() {
+        "AU-D" => (AU, SECONDS_PER_DAY),
+        "KM-D" => (1000.0, SECONDS_PER_DAY),
+        "KM-S" | "" => (1000.0, 1.0),
+        units => return Err(format!("unsupported output units {units}").into()),
+    };
+
+    let records = if data.contains(',') {
+        parse_csv_records(header, data)?
+    } else {
+        parse_text_records(data)?
+    };
+    if records.is_empty() {
+        return Err("no vectors between $$SOE and $$EOE".into());
+    }
+
+    Ok(VectorTable {
+        target,
+        center,
+        records: records
+            .into_iter()
+            .map(|record| VectorRecord {
+                julian_date: record.julian_date,
+                position: record.position * length,
+                velocity: record.velocity * length / time,
+            })
+            .collect(),
+    })
+}
+
+// "Target body name: Earth (399)   {source: DE441}" gives "Earth"
+fn header_value(header: &str, key: &str) -> Option<String> {
+    let line = header
+        .lines()
+        .find(|line| line.trim_start().starts_with(key))?;
+    let value = line.split_once(':')?.1;
+    let value = value.split(['(', '{']).next()?.trim();
+    Some(value.to_string())
+}
+
+// columns are named on the line just before $$SOE
+fn parse_csv_records(header: &str, data: &str) -> Result<Vec<VectorRecord>, Box<dyn Error>> {
+    let columns = header
+        .lines()
+        .rev()
+        .find(|line| line.contains("JDTDB"))
+        .ok_or("missing column names")?
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    let column = |name: &str| {
+        columns
+            .iter()
+            .position(|column| *column == name)
+            .ok_or(format!("missing column {name}"))
+    };
+    let indices = ["JDTDB", "X", "Y", "Z", "VX", "VY", "VZ"]
+        .map(column)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+
+    data.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+            let values = indices
+                .iter()
+                .map(|&i| {
+                    let field = fields.get(i).ok_or("line too short")?;
+                    field
+                        .parse::<f64>()
+                        .map_err(|_| format!("invalid number {field}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            Ok(VectorRecord {
+                julian_date: values[0],
+                position: DVec3::new(values[1], values[2], values[3]),
+                velocity: DVec3::new(values[4], values[5], values[6]),
+            })
+        })
+        .collect()
+}
+
+// every record starts with "2451545.000000000 = A.D. 2000-Jan-01 12:00:00.0000 TDB" followed by
+// lines of "X =-1.478E+08 Y = 1.572E+07 ..."
+fn parse_text_records(data: &str) -> Result<Vec<VectorRecord>, Box<dyn Error>> {
+    let mut records = vec![];
+    let mut current: Option<(f64, HashMap<&str, f64>)> = None;
+
+    for line in data.lines().filter(|line| line.contains('=')) {
+        if let Some((date, rest)) = line.split_once('=')
+            && let Ok(julian_date) = date.trim().parse::<f64>()
+            && rest.trim_start().starts_with(['A', 'B'])
+        {
+            if let Some(record) = current.take() {
+                records.push(text_record(record)?);
+            }
+            current = Some((julian_date, HashMap::new()));
+            continue;
+        }
+
+        let (_, values) = current.as_mut().ok_or("vectors before the first date")?;
+        let mut rest = line;
+        while let Some(eq) = rest.find('=') {
+            let key = rest[..eq].split_whitespace().last().unwrap_or_default();
+            let after = rest[eq + 1..].trim_start();
+            let end = after.find(char::is_whitespace).unwrap_or(after.len());
+            let value = after[..end]
+                .parse()
+                .map_err(|_| format!("invalid number {}", &after[..end]))?;
+
+            values.insert(key, value);
+            rest = &after[end..];
+        }
+    }
+
+    if let Some(record) = current {
+        records.push(text_record(record)?);
+    }
+
+    Ok(records)
+}
+
+fn text_record(
+    (julian_date, values): (f64, HashMap<&str, f64>),
+) -> Result<VectorRecord, Box<dyn Error>> {
+    let value = |key: &str| {
+        values
+            .get(key)
+            .copied()
+            .ok_or(format!("missing {key} at JD {julian_date}"))
+    };
+
+    Ok(VectorRecord {
+        julian_date,
+        position: DVec3::new(value("X")?, value("Y")?, value("Z")?),
+        velocity: DVec3::new(value("VX")?, value("VY")?, value("VZ")?),
+    })
+}
+
+/// Replaces the initial state of every scenario body with a table, at the epoch of the first
+/// record of the first table. Vectors are projected onto the reference plane of the tables,
+/// which is the ecliptic unless asked otherwise, since the simulation is flat.
+pub fn apply_vector_tables(
+    scenario: &mut Scenario,
+    tables: &[VectorTable],
+) -> Result<(), Box<dyn Error>> {
+    let epoch = tables
+        .first()
+        .and_then(|table| table.records.first())
+        .ok_or("no vectors to import")?
+        .julian_date;
+
+    // state of every target at the epoch, keyed by lowercase names as Horizons capitalizes
+    // them its own way
+    let mut states = HashMap::new();
+    for table in tables {
+        let record = table
+            .records
+            .iter()
+            .min_by(|a, b| {
+                (a.julian_date - epoch)
+                    .abs()
+                    .total_cmp(&(b.julian_date - epoch).abs())
+            })
+            .ok_or("empty table")?;
+        if (record.julian_date - epoch).abs() > MAX_EPOCH_DIFFERENCE {
+            return Err(format!("{} has no vectors at JD {epoch}", table.target).into());
+        }
+
+        states.insert(
+            table.target.to_lowercase(),
+            (table.center.to_lowercase(), *record),
+        );
+    }
+
+    for body in &mut scenario.bodies {
+        let name = body.name.to_lowercase();
+        let Some((center, record)) = states.get(&name) else {
+            continue;
+        };
+
+        // moons exported relative to their parent don't need the parent imported as well
+        if body
+            .parent
+            .as_ref()
+            .is_some_and(|parent| parent.to_lowercase() == *center)
+        {
+            body.position = project(record.position);
+            body.velocity = project(record.velocity);
+            continue;
+        }
+
+        let (position, velocity) = heliocentric(&name, &states, 0)?;
+        let (parent_position, parent_velocity) = match &body.parent {
+            Some(parent) => heliocentric(&parent.to_lowercase(), &states, 0)
+                .map_err(|_| format!("{} needs {parent} to be imported too", body.name))?,
+            None => (DVec3::ZERO, DVec3::ZERO),
+        };
+
+        body.position = project(position - parent_position);
+        body.velocity = project(velocity - parent_velocity);
+    }
+
+    scenario.epoch = epoch;
+    Ok(())
+}
+
+// follows the chain of centers down to the sun
+fn heliocentric(
+    name: &str,
+    states: &HashMap<String, (String, VectorRecord)>,
+    depth: usize,
+) -> Result<(DVec3, DVec3), Box<dyn Error>> {
+    if name == "sun" {
+        return Ok((DVec3::ZERO, DVec3::ZERO));
+    }
+    if depth > states.len() {
+        return Err(format!("centers of {name} go in a circle").into());
+    }
+
+    // a center that isn't imported can still be related through a sun table relative to it
+    let Some((center, record)) = states.get(name) else {
+        return match states.get("sun") {
+            Some((center, sun)) if center == name => Ok((-sun.position, -sun.velocity)),
+            _ => Err(format!("can't relate {name} to the sun").into()),
+        };
+    };
+
+    let (center_position, center_velocity) = heliocentric(center, states, depth + 1)?;
+    Ok((
+        center_position + record.position,
+        center_velocity + record.velocity,
+    ))
+}
+
+fn project(vector: DVec3) -> DVec2 {
+    vector.truncate()
+}
@@ -0,0 +1,10 @@
+//! Simulation core of the solar system viewer: n-body physics, orbital mechanics, the calendar and
+//! scenarios with their initial conditions. Nothing in here renders, so other tools can build on
+//! it without a window. Without the default `viewer` feature, which only the app needs, it
+//! depends on nothing of bevy but its math and task crates.
+
+pub mod constants;
+pub mod horizons;
+pub mod math;
+pub mod scenario;
+pub mod simulation;
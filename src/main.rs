@@ -1,42 +1,199 @@
+mod appearance;
 mod camera;
-mod constants;
-mod math;
+mod checkpoint;
+mod cli;
+mod collision;
+mod frame;
+mod headless;
 mod orbit;
+mod particle;
 mod planet;
+mod precession;
+mod recorder;
+mod ring;
+mod save;
+mod scaling;
 mod sun;
+mod trail;
 mod ui;
 
-use bevy::{math::DVec2, prelude::*, window::WindowResolution};
+use std::{error::Error, path::PathBuf, process};
+
+use bevy::{
+    log::LogPlugin,
+    prelude::*,
+    window::{MonitorSelection, WindowMode, WindowResolution},
+};
+use clap::Parser;
+
+use sun_orbit_simulation::{
+    horizons::{apply_vector_tables, read_vector_table},
+    math::{
+        calendar::SECONDS_PER_DAY,
+        physics::{ForceModel, GravitySolver},
+    },
+    scenario::Scenario,
+};
 
 use crate::{
+    appearance::Appearances,
     camera::CameraPlugin,
-    constants::{
-        EARTH_MASS, EARTH_POS_X, EARTH_RADIUS, EARTH_VEL_Y, JUPITER_MASS, JUPITER_POS_X,
-        JUPITER_RADIUS, JUPITER_VEL_Y, MARS_MASS, MARS_POS_X, MARS_RADIUS, MARS_VEL_Y,
-        MERCURY_MASS, MERCURY_POS_X, MERCURY_RADIUS, MERCURY_VEL_Y, NEPTUNE_MASS, NEPTUNE_POS_X,
-        NEPTUNE_RADIUS, NEPTUNE_VEL_Y, SATURN_MASS, SATURN_POS_X, SATURN_RADIUS, SATURN_VEL_Y,
-        URANUS_MASS, URANUS_POS_X, URANUS_RADIUS, URANUS_VEL_Y, VENUS_MASS, VENUS_POS_X,
-        VENUS_RADIUS, VENUS_VEL_Y,
-    },
+    checkpoint::CheckpointPlugin,
+    cli::Cli,
+    collision::CollisionPlugin,
+    frame::FramePlugin,
+    headless::{HeadlessPlugin, HeadlessRun},
     orbit::OrbitPlugin,
-    planet::{PlanetBundle, PlanetPlugin},
-    sun::SunPlugin,
-    ui::plugin::UiPlugin,
+    particle::ParticlePlugin,
+    planet::{InitialScenario, PlanetBundle, PlanetPlugin, Satellite, SimulationTime},
+    precession::PrecessionPlugin,
+    recorder::{Recorder, RecorderPlugin},
+    ring::{RingBundle, RingPlugin},
+    save::{SavePlugin, SaveRequests, read_save},
+    scaling::ScalingPlugin,
+    sun::{SunPlugin, spawn_sun},
+    trail::TrailPlugin,
+    ui::{
+        egui::{Gui, MAX_TIME_RATE, MIN_TIME_RATE},
+        plugin::UiPlugin,
+    },
 };
 
-fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "3D Solar System Simulation".to_string(),
-                resolution: WindowResolution::new(1920, 1080),
-                ..Default::default()
+fn main() -> AppExit {
+    let cli = Cli::parse();
+
+    let mut recorder = Recorder::default();
+    recorder.interval = cli.record_interval * SECONDS_PER_DAY;
+    if let Some(path) = cli.record
+        && let Err(err) = recorder.start(path.clone())
+    {
+        eprintln!("Couldn't record to {}: {err}", path.display());
+        process::exit(1);
+    }
+
+    let (mut scenario, appearances) = match &cli.scenario {
+        Some(path) => Scenario::read(path)
+            .and_then(|scenario| Ok((scenario, Appearances::read(path)?)))
+            .unwrap_or_else(|err| {
+                eprintln!("Couldn't read scenario {}: {err}", path.display());
+                process::exit(1);
             }),
+        None => (Scenario::solar_system(), Appearances::solar_system()),
+    };
+    if !cli.horizons.is_empty()
+        && let Err(err) = import_horizons(&mut scenario, &cli.horizons)
+    {
+        eprintln!("Couldn't import Horizons vectors: {err}");
+        process::exit(1);
+    }
+    scenario.add_asteroid_belt(cli.asteroids);
+    if cli.trojans > 0
+        && let Err(err) = scenario.add_trojans(cli.trojans, "Jupiter")
+    {
+        eprintln!("Couldn't add Trojans: {err}");
+        process::exit(1);
+    }
+
+    let mut sim_time = SimulationTime {
+        epoch: scenario.epoch,
+        ..Default::default()
+    };
+    sim_time.fixed_step = cli.fixed_step.map(f64::from);
+    if let Some(date) = cli.start_date {
+        sim_time.jump_to(date.julian_date());
+    }
+    let start = sim_time.target.unwrap_or(0.0);
+
+    let solver = match cli.barnes_hut {
+        Some(opening_angle) => GravitySolver::BarnesHut { opening_angle },
+        None => GravitySolver::Direct,
+    };
+
+    let mut app = App::new();
+    app.insert_resource(InitialScenario(scenario))
+        .insert_resource(recorder)
+        .insert_resource(sim_time);
+
+    if cli.headless {
+        let load = cli.load.map(|path| {
+            read_save(&path).unwrap_or_else(|err| {
+                eprintln!("Couldn't load {}: {err}", path.display());
+                process::exit(1);
+            })
+        });
+
+        return app
+            .add_plugins((MinimalPlugins, LogPlugin::default(), HeadlessPlugin))
+            .insert_resource(HeadlessRun {
+                end: start + cli.duration * SECONDS_PER_DAY,
+                forces: ForceModel {
+                    softening: cli.softening,
+                    close_encounters: cli.close_encounters,
+                    solver,
+                    ..Default::default()
+                },
+                integrator: cli.integrator,
+                output: cli.output,
+                load,
+                finished: false,
+            })
+            .add_plugins(RecorderPlugin)
+            .run();
+    }
+
+    let mut settings = Gui {
+        integrator: cli.integrator,
+        softening: cli.softening,
+        close_encounters: cli.close_encounters,
+        solver,
+        ..Default::default()
+    };
+    if let Some(rate) = cli.time_rate {
+        settings.time.rate = rate.clamp(MIN_TIME_RATE, MAX_TIME_RATE);
+    }
+
+    let mode = if cli.fullscreen {
+        WindowMode::BorderlessFullscreen(MonitorSelection::Primary)
+    } else {
+        WindowMode::Windowed
+    };
+
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            title: "3D Solar System Simulation".to_string(),
+            resolution: WindowResolution::new(cli.width, cli.height),
+            mode,
             ..Default::default()
-        }))
-        .add_plugins((UiPlugin, CameraPlugin, SunPlugin, PlanetPlugin, OrbitPlugin))
-        .add_systems(Startup, create_planets)
-        .run();
+        }),
+        ..Default::default()
+    }))
+    .insert_resource(settings)
+    .insert_resource(appearances)
+    .insert_resource(SaveRequests {
+        load: cli.load,
+        ..Default::default()
+    })
+    .add_plugins((
+        UiPlugin,
+        CameraPlugin,
+        SunPlugin,
+        PlanetPlugin,
+        ParticlePlugin,
+        RingPlugin,
+        OrbitPlugin,
+        ScalingPlugin,
+        FramePlugin,
+        TrailPlugin,
+        PrecessionPlugin,
+        CheckpointPlugin,
+        SavePlugin,
+        RecorderPlugin,
+        CollisionPlugin,
+    ))
+    // the order bodies are spawned in is the order forces are summed in, keep it fixed so runs
+    // are repeatable
+    .add_systems(Startup, create_planets.after(spawn_sun))
+    .run()
 }
 
 fn create_planets(
@@ -44,76 +201,52 @@ fn create_planets(
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    (scenario, appearances): (Res<InitialScenario>, Res<Appearances>),
+    mut sim_time: ResMut<SimulationTime>,
 ) {
-    let planets = [
-        (
-            MERCURY_MASS,
-            MERCURY_POS_X,
-            MERCURY_VEL_Y,
-            MERCURY_RADIUS,
-            "textures/mercury.jpg",
-        ),
-        (
-            VENUS_MASS,
-            VENUS_POS_X,
-            VENUS_VEL_Y,
-            VENUS_RADIUS,
-            "textures/venus.jpg",
-        ),
-        (
-            EARTH_MASS,
-            EARTH_POS_X,
-            EARTH_VEL_Y,
-            EARTH_RADIUS,
-            "textures/earth.jpg",
-        ),
-        (
-            MARS_MASS,
-            MARS_POS_X,
-            MARS_VEL_Y,
-            MARS_RADIUS,
-            "textures/mars.jpg",
-        ),
-        (
-            JUPITER_MASS,
-            JUPITER_POS_X,
-            JUPITER_VEL_Y,
-            JUPITER_RADIUS,
-            "textures/jupiter.jpg",
-        ),
-        (
-            SATURN_MASS,
-            SATURN_POS_X,
-            SATURN_VEL_Y,
-            SATURN_RADIUS,
-            "textures/saturn.jpg",
-        ),
-        (
-            URANUS_MASS,
-            URANUS_POS_X,
-            URANUS_VEL_Y,
-            URANUS_RADIUS,
-            "textures/uranus.jpg",
-        ),
-        (
-            NEPTUNE_MASS,
-            NEPTUNE_POS_X,
-            NEPTUNE_VEL_Y,
-            NEPTUNE_RADIUS,
-            "textures/neptune.jpg",
-        ),
-    ];
-
-    for (mass, pos, vel, radius, texture) in planets {
-        cmds.spawn(PlanetBundle::new(
+    sim_time.epoch = scenario.epoch;
+
+    let mut spawned = Vec::new();
+
+    for body in scenario.initial_bodies() {
+        let appearance = appearances.get(&body.config.name);
+        let mut planet = cmds.spawn(PlanetBundle::new(
             &asset_server,
             &mut meshes,
             &mut materials,
-            mass,
-            DVec2::new(pos, 0.0),
-            DVec2::new(0.0, vel),
-            radius,
-            texture,
+            body.config,
+            &appearance,
+            body.position,
+            body.velocity,
         ));
+
+        if let Some((parent, innermost_distance)) = body.satellite {
+            planet.insert(Satellite {
+                parent: spawned[parent],
+                innermost_distance,
+            });
+        }
+
+        if let Some(ring) = &appearance.ring {
+            planet.with_child(RingBundle::new(
+                &mut meshes,
+                &mut materials,
+                &mut images,
+                ring,
+                appearance.radius,
+            ));
+        }
+
+        spawned.push(planet.id());
     }
 }
+
+fn import_horizons(scenario: &mut Scenario, paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    let tables = paths
+        .iter()
+        .map(|path| read_vector_table(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    apply_vector_tables(scenario, &tables)
+}
@@ -0,0 +1,260 @@
+use bevy_math::DVec2;
+
+use crate::{constants::G, math::physics::BodyState};
+
+// cells this deep are far smaller than any body, whatever is still in one of them shares a leaf
+const MAX_DEPTH: usize = 48;
+
+/// Barnes-Hut tree over the bodies of the (flat) simulation, so a quadtree. Far away groups of
+/// bodies pull like a single one sitting at their center of mass, which brings the cost of the
+/// accelerations down from O(n²) to O(n log n).
+pub struct QuadTree {
+    nodes: Vec<Node>,
+    order: Vec<usize>, // body indices, the bodies of every node are next to each other
+}
+
+struct Node {
+    mass: f64,
+    center_of_mass: DVec2,
+    center: DVec2,
+    size: f64,              // side length of the square cell
+    children: usize,        // index of the first of 4 consecutive children, 0 for leaves
+    bodies: (usize, usize), // range in `order`
+}
+
+impl QuadTree {
+    pub fn new(bodies: &[BodyState]) -> Self {
+        let mut tree = Self {
+            nodes: Vec::new(),
+            order: (0..bodies.len()).collect(),
+        };
+        if bodies.is_empty() {
+            return tree;
+        }
+
+        let (min, max) = bodies.iter().fold(
+            (DVec2::splat(f64::INFINITY), DVec2::splat(f64::NEG_INFINITY)),
+            |(min, max), body| (min.min(body.position), max.max(body.position)),
+        );
+        let size = (max - min).max_element().max(f64::MIN_POSITIVE);
+
+        tree.nodes.push(Node::EMPTY);
+        tree.build(bodies, 0, (min + max) / 2.0, size, (0, bodies.len()), 0);
+        tree
+    }
+
+    fn build(
+        &mut self,
+        bodies: &[BodyState],
+        node: usize,
+        center: DVec2,
+        size: f64,
+        range: (usize, usize),
+        depth: usize,
+    ) {
+        let indices = &mut self.order[range.0..range.1];
+        let mass = indices.iter().map(|&i| bodies[i].mass).sum::<f64>();
+        let center_of_mass = if mass > 0.0 {
+            indices
+                .iter()
+                .map(|&i| bodies[i].mass * bodies[i].position)
+                .sum::<DVec2>()
+                / mass
+        } else {
+            center
+        };
+
+        self.nodes[node] = Node {
+            mass,
+            center_of_mass,
+            center,
+            size,
+            children: 0,
+            bodies: range,
+        };
+        if indices.len() <= 1 || depth == MAX_DEPTH {
+            return;
+        }
+
+        let quadrant = |position: DVec2| {
+            usize::from(position.x >= center.x) + 2 * usize::from(position.y >= center.y)
+        };
+        indices.sort_unstable_by_key(|&i| quadrant(bodies[i].position));
+        let mut ends = [0; 4];
+        for &i in indices.iter() {
+            ends[quadrant(bodies[i].position)] += 1;
+        }
+
+        let children = self.nodes.len();
+        self.nodes[node].children = children;
+        self.nodes.extend((0..4).map(|_| Node::EMPTY));
+
+        let mut start = range.0;
+        for (child, count) in ends.into_iter().enumerate() {
+            let offset = DVec2::new(
+                if child % 2 == 0 { -0.25 } else { 0.25 },
+                if child < 2 { -0.25 } else { 0.25 },
+            );
+            let end = start + count;
+            self.build(
+                bodies,
+                children + child,
+                center + offset * size,
+                size / 2.0,
+                (start, end),
+                depth + 1,
+            );
+            start = end;
+        }
+    }
+
+    /// Acceleration at `position` caused by all bodies except `skip`. Cells that look smaller
+    /// than `opening_angle` (in radians) from there are treated as a single body, 0 sums every
+    /// body directly.
+    pub fn acceleration_at(
+        &self,
+        bodies: &[BodyState],
+        position: DVec2,
+        skip: Option<usize>,
+        opening_angle: f64,
+        softening: f64,
+    ) -> DVec2 {
+        let pull = |mass: f64, at: DVec2| {
+            let d = at - position;
+            let r = (d.length_squared() + softening * softening).sqrt();
+            if r == 0.0 {
+                return DVec2::ZERO; // coincident bodies don't pull each other anywhere
+            }
+            G * mass * d / (r * r * r)
+        };
+
+        let mut acceleration = DVec2::ZERO;
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.mass == 0.0 {
+                continue;
+            }
+
+            if node.children == 0 {
+                for &i in &self.order[node.bodies.0..node.bodies.1] {
+                    if Some(i) != skip {
+                        acceleration += pull(bodies[i].mass, bodies[i].position);
+                    }
+                }
+                continue;
+            }
+
+            // a cell the position is in is always opened, it could hold the skipped body
+            let inside = (position - node.center).abs().max_element() <= node.size / 2.0;
+            let distance_squared = (node.center_of_mass - position).length_squared();
+            if !inside && node.size * node.size < opening_angle * opening_angle * distance_squared {
+                acceleration += pull(node.mass, node.center_of_mass);
+            } else {
+                stack.extend(node.children..node.children + 4);
+            }
+        }
+
+        acceleration
+    }
+
+    /// Body other than `skip` pulling hardest on `position`. Cells that can't hold a body pulling
+    /// harder than the strongest one found so far are never opened, so this is exact.
+    pub fn strongest_at(
+        &self,
+        bodies: &[BodyState],
+        position: DVec2,
+        skip: Option<usize>,
+        softening: f64,
+    ) -> Option<usize> {
+        let softening2 = softening * softening;
+        // no body in the cell can be heavier than all of them or closer than its edge
+        let bound = |node: &Node| {
+            let outside = ((position - node.center).abs() - node.size / 2.0).max(DVec2::ZERO);
+            node.mass / (outside.length_squared() + softening2)
+        };
+
+        let mut strongest = None;
+        let mut strongest_pull = f64::NEG_INFINITY;
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.bodies.0 == node.bodies.1 || bound(node) < strongest_pull {
+                continue;
+            }
+
+            if node.children == 0 {
+                for &i in &self.order[node.bodies.0..node.bodies.1] {
+                    let pull = bodies[i].mass
+                        / (bodies[i].position.distance_squared(position) + softening2);
+                    if Some(i) != skip && pull > strongest_pull {
+                        strongest = Some(i);
+                        strongest_pull = pull;
+                    }
+                }
+                continue;
+            }
+
+            // the most promising child is opened first, so the others can mostly be skipped
+            let mut children = [0, 1, 2, 3].map(|child| node.children + child);
+            children.sort_by(|&a, &b| bound(&self.nodes[a]).total_cmp(&bound(&self.nodes[b])));
+            stack.extend(children);
+        }
+
+        strongest
+    }
+
+    /// Bodies sitting in the box from `min` to `max`, edges included.
+    pub fn within(&self, bodies: &[BodyState], min: DVec2, max: DVec2) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            let half = node.size / 2.0;
+            let apart =
+                (node.center - half).cmpgt(max).any() || (node.center + half).cmplt(min).any();
+            if node.bodies.0 == node.bodies.1 || apart {
+                continue;
+            }
+
+            if node.children == 0 {
+                found.extend(
+                    self.order[node.bodies.0..node.bodies.1]
+                        .iter()
+                        .filter(|&&i| {
+                            let position = bodies[i].position;
+                            position.cmpge(min).all() && position.cmple(max).all()
+                        }),
+                );
+                continue;
+            }
+
+            stack.extend(node.children..node.children + 4);
+        }
+
+        found
+    }
+}
+
+impl Node {
+    const EMPTY: Self = Self {
+        mass: 0.0,
+        center_of_mass: DVec2::ZERO,
+        center: DVec2::ZERO,
+        size: 0.0,
+        children: 0,
+        bodies: (0, 0),
+    };
+}
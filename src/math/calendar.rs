@@ -0,0 +1,165 @@
+use std::fmt;
+
+pub const J2000: f64 = 2_451_545.0; // 2000-01-01 12:00
+pub const SECONDS_PER_DAY: f64 = 86_400.0;
+
+const MILLISECONDS_PER_DAY: i64 = 86_400_000;
+
+/// Calendar date, Julian before 1582-10-15 and Gregorian after, like astronomers count them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Date {
+    pub year: i32, // astronomical, so 0 is 1 BC
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: f64,
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            hour: 0,
+            minute: 0,
+            second: 0.0,
+        }
+    }
+
+    /// Meeus, Astronomical Algorithms, chapter 7.
+    pub fn julian_date(&self) -> f64 {
+        let (year, month) = if self.month <= 2 {
+            (self.year - 1, self.month + 12)
+        } else {
+            (self.year, self.month)
+        };
+
+        let b = if (self.year, self.month, self.day) >= (1582, 10, 15) {
+            let a = year.div_euclid(100);
+            2 - a + a.div_euclid(4)
+        } else {
+            0
+        };
+
+        let day = self.day as f64
+            + (self.hour as f64 + self.minute as f64 / 60.0 + self.second / 3600.0) / 24.0;
+
+        (365.25 * (year as f64 + 4716.0)).floor()
+            + (30.6001 * (month as f64 + 1.0)).floor()
+            + day
+            + b as f64
+            - 1524.5
+    }
+
+    pub fn from_julian_date(julian_date: f64) -> Self {
+        // whole milliseconds, so the time of day doesn't come out as 59.999 seconds
+        let milliseconds = ((julian_date + 0.5) * MILLISECONDS_PER_DAY as f64).round() as i64;
+        let z = milliseconds.div_euclid(MILLISECONDS_PER_DAY);
+        let time_of_day = milliseconds.rem_euclid(MILLISECONDS_PER_DAY);
+
+        let a = if z < 2_299_161 {
+            z
+        } else {
+            let alpha = ((z as f64 - 1_867_216.25) / 36_524.25).floor() as i64;
+            z + 1 + alpha - alpha.div_euclid(4)
+        };
+        let b = a + 1524;
+        let c = ((b as f64 - 122.1) / 365.25).floor() as i64;
+        let d = (365.25 * c as f64).floor() as i64;
+        let e = ((b - d) as f64 / 30.6001).floor() as i64;
+
+        let day = b - d - (30.6001 * e as f64).floor() as i64;
+        let month = if e < 14 { e - 1 } else { e - 13 };
+        let year = if month > 2 { c - 4716 } else { c - 4715 };
+
+        Self {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+            hour: (time_of_day / 3_600_000) as u32,
+            minute: (time_of_day / 60_000 % 60) as u32,
+            second: (time_of_day % 60_000) as f64 / 1000.0,
+        }
+    }
+
+    /// Parses `YYYY-MM-DD`, optionally followed by `HH:MM` or `HH:MM:SS`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.split_whitespace();
+        let date = parts.next()?;
+        let time = parts.next();
+        if parts.next().is_some() {
+            return None;
+        }
+
+        // a leading minus belongs to the year
+        let (sign, date) = match date.strip_prefix('-') {
+            Some(date) => (-1, date),
+            None => (1, date),
+        };
+        let mut fields = date.split('-');
+        let year = sign * fields.next()?.parse::<i32>().ok()?;
+        let month = fields.next()?.parse().ok()?;
+        let day = fields.next()?.parse().ok()?;
+        if fields.next().is_some()
+            || !(1..=12).contains(&month)
+            || !(1..=days_in_month(year, month)).contains(&day)
+        {
+            return None;
+        }
+        // skipped when switching from the Julian to the Gregorian calendar
+        if (year, month) == (1582, 10) && (5..15).contains(&day) {
+            return None;
+        }
+
+        let mut result = Self::new(year, month, day);
+        if let Some(time) = time {
+            let mut fields = time.split(':');
+            result.hour = fields.next()?.parse().ok()?;
+            result.minute = fields.next()?.parse().ok()?;
+            result.second = fields.next().map_or(Some(0.0), |s| s.parse().ok())?;
+            if fields.next().is_some()
+                || result.hour > 23
+                || result.minute > 59
+                || !(0.0..60.0).contains(&result.second)
+            {
+                return None;
+            }
+        }
+
+        Some(result)
+    }
+}
+
+// leap years are every 4 years in the Julian calendar, without most century years in the
+// Gregorian one
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let leap = if year > 1582 {
+        year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+    } else {
+        year.rem_euclid(4) == 0
+    };
+
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second.floor()
+        )
+    }
+}
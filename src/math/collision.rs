@@ -0,0 +1,101 @@
+use bevy_math::DVec2;
+
+use crate::{
+    constants::G,
+    math::{
+        barnes_hut::QuadTree,
+        physics::{BodyState, orbital_period},
+    },
+};
+
+// part of their orbit around each other two bodies may cover in a tick for their path to still
+// count as a straight line, the chord stays within about 1% of the distance
+const STRAIGHT_ORBIT_FRACTION: f64 = 0.05;
+
+/// Pairs of bodies that may have touched while moving from their `previous` positions, `(i, j)`
+/// with `i < j` in order. Only bodies whose paths, widened by their `radii`, overlap are paired,
+/// they're looked up in a quadtree so this isn't O(n²) when the bodies are spread out.
+pub fn possible_contacts(
+    bodies: &[BodyState],
+    previous: &[DVec2],
+    radii: &[f64],
+) -> Vec<(usize, usize)> {
+    let sweep = |i: usize| {
+        let (from, to) = (previous[i], bodies[i].position);
+        (from.min(to) - radii[i], from.max(to) + radii[i])
+    };
+    // no path reaches further than this from where its body ended up
+    let reach = (0..bodies.len())
+        .map(|i| bodies[i].position.distance(previous[i]) + radii[i])
+        .fold(0.0, f64::max);
+
+    let tree = QuadTree::new(bodies);
+    let mut pairs = Vec::new();
+    for i in 0..bodies.len() {
+        let (min, max) = sweep(i);
+        let mut nearby = tree.within(bodies, min - reach, max + reach);
+        nearby.retain(|&j| {
+            let (other_min, other_max) = sweep(j);
+            j > i && other_min.cmple(max).all() && min.cmple(other_max).all()
+        });
+        nearby.sort_unstable();
+        pairs.extend(nearby.into_iter().map(|j| (i, j)));
+    }
+    pairs
+}
+
+/// Whether two bodies touched during a tick of `dt` seconds that started at the `previous`
+/// positions. Fast bodies can't skip through each other between two checks, unless they orbit
+/// each other: a long tick doesn't follow a straight line around the orbit, so only where they
+/// ended up counts.
+pub fn touched(
+    a: &BodyState,
+    a_previous: DVec2,
+    b: &BodyState,
+    b_previous: DVec2,
+    reach: f64,
+    dt: f64,
+) -> bool {
+    let position = a.position - b.position;
+    let velocity = a.velocity - b.velocity;
+    let mu = G * (a.mass + b.mass);
+
+    let bound = velocity.length_squared() / 2.0 < mu / position.length();
+    if bound
+        && dt.abs() > STRAIGHT_ORBIT_FRACTION * orbital_period(position, velocity, a.mass + b.mass)
+    {
+        return position.length() <= reach;
+    }
+    swept_contact(a_previous - b_previous, position, reach)
+}
+
+/// Whether two bodies touched while moving from `previous` to `current`, both positions of one
+/// relative to the other. The motion in between is taken as a straight line.
+pub fn swept_contact(previous: DVec2, current: DVec2, reach: f64) -> bool {
+    let motion = current - previous;
+    let length_squared = motion.length_squared();
+
+    // closest point of the path to the other body
+    let t = if length_squared > 0.0 {
+        (-previous.dot(motion) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (previous + motion * t).length() <= reach
+}
+
+/// Perfectly inelastic collision, the merged body sits at the center of mass and carries the
+/// total momentum.
+pub fn merge(a: &BodyState, b: &BodyState) -> BodyState {
+    let mass = a.mass + b.mass;
+    BodyState {
+        mass,
+        position: (a.mass * a.position + b.mass * b.position) / mass,
+        velocity: (a.mass * a.velocity + b.mass * b.velocity) / mass,
+    }
+}
+
+/// Radius of two bodies merged into one, assuming they're equally dense.
+pub fn merged_radius(a: f64, b: f64) -> f64 {
+    (a.powi(3) + b.powi(3)).cbrt()
+}
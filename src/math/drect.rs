@@ -1,4 +1,4 @@
-use bevy::math::DVec2;
+use bevy_math::DVec2;
 
 #[derive(Clone, Copy, PartialEq)]
 pub struct DRect {
@@ -7,7 +7,7 @@ pub struct DRect {
 }
 
 impl DRect {
-    pub fn new(min: DVec2, max: DVec2) -> Self {
+    pub const fn new(min: DVec2, max: DVec2) -> Self {
         Self { min, max }
     }
 
@@ -1,2 +1,8 @@
+pub mod barnes_hut;
+pub mod calendar;
+pub mod collision;
 pub mod drect;
+pub mod orbit;
+pub mod particles;
 pub mod physics;
+pub mod scaling;
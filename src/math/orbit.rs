@@ -0,0 +1,168 @@
+use bevy_math::DVec2;
+
+use crate::math::{
+    drect::DRect,
+    physics::{orbital_period, semi_major_axis, velocity_verlet},
+};
+
+const MAX_POINTS: usize = 128;
+const ORBIT_STEPS: f64 = 4096.0; // simulated steps per revolution
+
+#[derive(PartialEq)]
+struct Quarter {
+    // Y direction (-1, 1) in which the current point will be ahead of starting position
+    direction: DVec2,
+    bounds: DRect,
+}
+
+const QUARTERS: [Quarter; 4] = [
+    // top right
+    Quarter {
+        direction: DVec2::new(-1.0, 0.0),
+        bounds: DRect::new(
+            DVec2::new(0.0, f64::INFINITY),
+            DVec2::new(f64::INFINITY, 0.0),
+        ),
+    },
+    // bottom right
+    Quarter {
+        direction: DVec2::new(0.0, 1.0),
+        bounds: DRect::new(
+            DVec2::new(0.0, 0.0),
+            DVec2::new(f64::INFINITY, -f64::INFINITY),
+        ),
+    },
+    // bottom left
+    Quarter {
+        direction: DVec2::new(1.0, 0.0),
+        bounds: DRect::new(
+            DVec2::new(-f64::INFINITY, 0.0),
+            DVec2::new(0.0, -f64::INFINITY),
+        ),
+    },
+    // top left
+    Quarter {
+        direction: DVec2::new(0.0, -1.0),
+        bounds: DRect::new(
+            DVec2::new(-f64::INFINITY, f64::INFINITY),
+            DVec2::new(0.0, 0.0),
+        ),
+    },
+];
+
+fn find_quarter_idx(pos: DVec2, quarters: &[Quarter]) -> usize {
+    for (i, quarter) in quarters.iter().enumerate() {
+        if quarter.bounds.contains(pos) {
+            return i;
+        }
+    }
+    unreachable!("point not inside any quarter");
+}
+
+/// One revolution of a two-body orbit starting at `position`, thinned out to about
+/// `MAX_POINTS` points. None for bodies that aren't bound, they never come back around.
+pub fn compute_orbit(position: DVec2, velocity: DVec2, central_mass: f64) -> Option<Vec<DVec2>> {
+    let semi_major_axis = semi_major_axis(position, velocity, central_mass);
+    if !semi_major_axis.is_finite() || semi_major_axis <= 0.0 {
+        return None;
+    }
+
+    let quarters = &QUARTERS;
+    let sub_quarter_overflow = |idx: usize| -> usize {
+        if idx > 0 && idx != 1 {
+            idx - 1
+        } else {
+            quarters.len() - 1
+        }
+    };
+    let add_quarter_overflow = |idx: usize| -> usize {
+        if idx >= quarters.len() - 1 {
+            0
+        } else {
+            idx + 1
+        }
+    };
+
+    let mut position = position;
+    let mut velocity = velocity;
+    let starting_position = position;
+
+    // same amount of steps for every orbit, no matter if it takes days or centuries
+    let dt = orbital_period(position, velocity, central_mass) / ORBIT_STEPS;
+
+    let starting_quarter = find_quarter_idx(position, quarters);
+    let mut last_quarter = starting_quarter;
+    let mut first = true;
+
+    let mut points = vec![];
+    loop {
+        // compute next position
+        let (pos_new, vel_new) = velocity_verlet(dt, position, velocity, central_mass);
+        position = pos_new;
+        velocity = vel_new;
+        points.push(pos_new);
+
+        // check if we made full revolution
+        let current_quarter = find_quarter_idx(pos_new, quarters);
+        if current_quarter == starting_quarter
+            && last_quarter == add_quarter_overflow(starting_quarter)
+        {
+            first = false;
+        }
+
+        last_quarter = current_quarter;
+        if !first && current_quarter == sub_quarter_overflow(starting_quarter) {
+            break;
+        }
+    }
+
+    let mut last_point = None;
+    for (i, point) in points.clone().into_iter().enumerate().rev() {
+        let quarter = find_quarter_idx(point, quarters);
+
+        if quarter == last_quarter {
+            points.remove(i);
+        } else if quarter == starting_quarter {
+            let delta = point - starting_position;
+            let direction = quarters[quarter].direction;
+
+            let x_positive = delta.x.is_sign_positive();
+            let y_positive = delta.y.is_sign_positive();
+            if (x_positive && direction.x == 1.0)
+                || (!x_positive && direction.x == -1.0)
+                || (y_positive && direction.y == 1.0)
+                || (!y_positive && direction.y == -1.0)
+            {
+                last_point = Some(point);
+                points.remove(i);
+            }
+        } else {
+            break;
+        }
+    }
+
+    let take_nth = points.len() / MAX_POINTS;
+    let mut temp = every_nth_element(points, take_nth);
+    if let Some(p) = last_point {
+        temp.push(p);
+    }
+    Some(temp)
+}
+
+fn every_nth_element(mut values: Vec<DVec2>, n: usize) -> Vec<DVec2> {
+    let mut first = true;
+    let mut c = 0;
+
+    values.retain(|_| {
+        // always take the first element
+        if first {
+            first = false;
+            return true;
+        }
+
+        c += 1;
+        c % n == 0
+    });
+
+    values
+}
@@ -0,0 +1,67 @@
+use bevy_math::DVec2;
+use bevy_tasks::{ComputeTaskPool, ParallelSliceMut, TaskPool};
+
+use crate::{constants::G, math::physics::BodyState};
+
+const PARTICLE_CHUNK: usize = 4096; // particles per task when advancing them in parallel
+
+/// Position and velocity of a test particle. It's pulled by the bodies but too light to pull
+/// back, so particles don't feel each other either and can all be moved independently.
+#[derive(Clone, Copy)]
+pub struct ParticleState {
+    pub position: DVec2,
+    pub velocity: DVec2,
+}
+
+/// Where the bodies were at the start and after every substep of a step, which is all the test
+/// particles need to follow them afterwards.
+pub struct BodyTrack {
+    steps: Vec<f64>,             // length of every substep
+    states: Vec<Vec<BodyState>>, // one more than there are substeps
+}
+
+impl BodyTrack {
+    pub fn new(bodies: &[BodyState]) -> Self {
+        Self {
+            steps: vec![],
+            states: vec![bodies.to_vec()],
+        }
+    }
+
+    /// Adds the bodies as they are after the next substep, `step` long.
+    pub fn push(&mut self, step: f64, bodies: &[BodyState]) {
+        self.steps.push(step);
+        self.states.push(bodies.to_vec());
+    }
+
+    /// Moves a particle along the whole track with velocity Verlet, the bodies at both ends of
+    /// every substep are already known.
+    pub fn advance(&self, particle: &mut ParticleState, softening: f64) {
+        for (&dt, pair) in self.steps.iter().zip(self.states.windows(2)) {
+            let a1 = gravity_at(particle.position, &pair[0], softening);
+            particle.position += particle.velocity * dt + 0.5 * a1 * dt * dt;
+
+            let a2 = gravity_at(particle.position, &pair[1], softening);
+            particle.velocity += 0.5 * (a1 + a2) * dt;
+        }
+    }
+
+    /// Same as [`Self::advance`] for a whole batch of particles, spread over the compute threads.
+    pub fn advance_all(&self, mut particles: &mut [ParticleState], softening: f64) {
+        let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+        particles.par_chunk_map_mut(pool, PARTICLE_CHUNK, |_, chunk| {
+            for particle in chunk {
+                self.advance(particle, softening);
+            }
+        });
+    }
+}
+
+/// Acceleration at `position` caused by all bodies, with the same softening they feel.
+pub fn gravity_at(position: DVec2, bodies: &[BodyState], softening: f64) -> DVec2 {
+    bodies.iter().fold(DVec2::ZERO, |acceleration, body| {
+        let d = body.position - position;
+        let r = (d.length_squared() + softening * softening).sqrt();
+        acceleration + G * body.mass * d / (r * r * r)
+    })
+}
@@ -1,54 +1,467 @@
-use bevy::math::{DVec2, Vec2};
+use std::{
+    f64::consts::{PI, TAU},
+    str::FromStr,
+};
 
-use crate::constants::{DISTANCE_SCALE, G, SUN_MASS};
+use bevy_math::DVec2;
+use bevy_tasks::{ComputeTaskPool, ParallelSlice, TaskPool};
+use serde::{Deserialize, Serialize};
 
-const A: f64 = 7e-25;
-const CUT_OUT: f64 = 249261000000.0;
-const MIN_VALUE: f64 = 1.0;
+use crate::{
+    constants::{C, G},
+    math::{barnes_hut::QuadTree, particles::BodyTrack},
+};
 
-pub fn compute_acceleration(pos: DVec2, mass: f64) -> DVec2 {
-    // calculate the distance to earth from sun's center
-    // but because our sun is at (0.0, 0.0) we can omit it from equation
-    let d = DVec2::new(-pos.x, -pos.y);
-    let r = (d.x * d.x + d.y * d.y).sqrt(); // distance between sun's and earth's center (in meters)
+// longest step the n-body integrator takes, larger time deltas are split into substeps so the
+// moons (Io goes around Jupiter in under 2 days) stay on their orbits
+const MAX_TIME_STEP: f64 = 60.0 * 60.0;
+
+// with close encounter handling, fraction of the closest pair's dynamical time a step may take,
+// about 300 steps per orbit
+const ENCOUNTER_STEP_FRACTION: f64 = 0.02;
+const MIN_ENCOUNTER_STEP: f64 = 1.0; // so bodies passing right through each other still finish
+// how much two bodies flying past each other have to bend their paths to be an encounter, as the
+// ratio of their potential to their kinetic energy
+const ENCOUNTER_FOCUSING: f64 = 0.01;
+// how eccentric the orbit of two bodies around each other has to be to be an encounter
+const ENCOUNTER_ECCENTRICITY: f64 = 0.5;
+
+// bodies per task when walking the Barnes-Hut tree in parallel
+const TREE_WALK_CHUNK: usize = 1024;
+
+/// Opening angle the Barnes-Hut solver starts with, a common tradeoff between speed and accuracy.
+pub const DEFAULT_OPENING_ANGLE: f64 = 0.5;
+
+/// Mass, position and velocity of a single body, as seen by the integrator.
+#[derive(Clone, Copy)]
+pub struct BodyState {
+    pub mass: f64,
+    pub position: DVec2,
+    pub velocity: DVec2,
+}
 
-    // calculate gravitational forces (F) for each axis
-    let f = (G * ((SUN_MASS * mass) / (r * r))) * (d / r);
+/// Acceleration of a body at `pos` orbiting a `central_mass` sitting at (0.0, 0.0).
+pub fn compute_acceleration(pos: DVec2, central_mass: f64) -> DVec2 {
+    // calculate the distance to the body from the center
+    // but because the center is at (0.0, 0.0) we can omit it from equation
+    let d = DVec2::new(-pos.x, -pos.y);
+    let r = (d.x * d.x + d.y * d.y).sqrt(); // distance between centers (in meters)
 
-    // calculate acceleration: F=ma -> a=F/m
-    f / mass
+    // F=ma -> a=F/m, so the mass of the orbiting body cancels out
+    (G * (central_mass / (r * r))) * (d / r)
 }
 
 pub fn velocity_verlet(
     dt: f64,
     mut position: DVec2,
     mut velocity: DVec2,
-    mass: f64,
+    central_mass: f64,
 ) -> (DVec2, DVec2) {
-    let a1 = compute_acceleration(position, mass); // first acceleration
+    let a1 = compute_acceleration(position, central_mass); // first acceleration
     let v_temp = velocity; // temporary velocity only for calculations
     position += v_temp * dt + 0.5 * a1 * dt * dt;
 
-    let a2 = compute_acceleration(position, mass); // second acceleration
+    let a2 = compute_acceleration(position, central_mass); // second acceleration
     velocity += 0.5 * (a1 + a2) * dt;
 
     (position, velocity)
 }
 
-fn compute_planet_distance_scale(total_dist: f64) -> f64 {
-    let c = -(A * CUT_OUT * CUT_OUT);
+/// Which terms go into the acceleration of the n-body integrator, and how it treats close
+/// passes.
+#[derive(Clone, Copy, Default)]
+pub struct ForceModel {
+    pub relativity: bool,       // first post-Newtonian correction
+    pub softening: f64,         // Plummer softening length in meters, 0 for plain Newtonian gravity
+    pub close_encounters: bool, // shorten the steps while two bodies are close to each other
+    pub solver: GravitySolver,
+}
 
-    let scale = if total_dist > -CUT_OUT && total_dist < CUT_OUT {
-        MIN_VALUE
-    } else {
-        ((A * total_dist * total_dist + c) + MIN_VALUE).powf(0.3)
+/// How the Newtonian pull between the bodies is summed up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum GravitySolver {
+    /// Every pair of bodies, exact but O(n²).
+    #[default]
+    Direct,
+    /// Far away groups of bodies are approximated by their center of mass, O(n log n). Smaller
+    /// opening angles (in radians) are more accurate and slower.
+    BarnesHut { opening_angle: f64 },
+}
+
+/// Accelerations of all bodies caused by each other, the sun included.
+pub fn compute_accelerations(bodies: &[BodyState], forces: ForceModel) -> Vec<DVec2> {
+    let opening_angle = match forces.solver {
+        GravitySolver::Direct => return direct_accelerations(bodies, forces),
+        GravitySolver::BarnesHut { opening_angle } => opening_angle,
+    };
+
+    // every body walks the tree on its own, so the threads don't change the result. The
+    // relativistic correction only goes between each body and the one pulling hardest on it, the
+    // sun for planets and the planet for moons, it's far too small to matter for any other pair.
+    let tree = QuadTree::new(bodies);
+    let pool = ComputeTaskPool::get_or_init(TaskPool::default);
+    let (mut accelerations, partners): (Vec<_>, Vec<_>) = bodies
+        .par_chunk_map(pool, TREE_WALK_CHUNK, |chunk, chunk_bodies| {
+            chunk_bodies
+                .iter()
+                .enumerate()
+                .map(|(i, body)| {
+                    let i = chunk * TREE_WALK_CHUNK + i;
+                    let softening = forces.softening;
+                    let acceleration = tree.acceleration_at(
+                        bodies,
+                        body.position,
+                        Some(i),
+                        opening_angle,
+                        softening,
+                    );
+                    let partner = forces
+                        .relativity
+                        .then(|| tree.strongest_at(bodies, body.position, Some(i), softening))
+                        .flatten();
+                    (acceleration, partner)
+                })
+                .collect::<Vec<_>>()
+        })
+        .concat()
+        .into_iter()
+        .unzip();
+
+    let mut pairs = partners
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, j)| j.map(|j| (i.min(j), i.max(j))))
+        .collect::<Vec<_>>();
+    pairs.sort_unstable();
+    pairs.dedup();
+    for (i, j) in pairs {
+        let (a_i, a_j) = relativistic_correction(&bodies[i], &bodies[j]);
+        accelerations[i] += a_i;
+        accelerations[j] += a_j;
+    }
+
+    accelerations
+}
+
+fn direct_accelerations(bodies: &[BodyState], forces: ForceModel) -> Vec<DVec2> {
+    let mut accelerations = vec![DVec2::ZERO; bodies.len()];
+
+    // every pair is visited once and the force is applied to both of its bodies
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            let d = bodies[j].position - bodies[i].position;
+            // the bodies act like clouds of size `softening`, so the force stays finite at 0
+            let r = (d.length_squared() + forces.softening * forces.softening).sqrt();
+            let direction = if r > 0.0 {
+                G * d / (r * r * r)
+            } else {
+                DVec2::ZERO // coincident bodies don't pull each other anywhere
+            };
+
+            accelerations[i] += direction * bodies[j].mass;
+            accelerations[j] -= direction * bodies[i].mass;
+
+            if forces.relativity {
+                let (a_i, a_j) = relativistic_correction(&bodies[i], &bodies[j]);
+                accelerations[i] += a_i;
+                accelerations[j] += a_j;
+            }
+        }
+    }
+
+    accelerations
+}
+
+/// 1PN Schwarzschild correction for a pair of bodies, each one treated as orbiting the other.
+/// It's exact for a light body around a heavy one, which is the only case where it matters.
+fn relativistic_correction(a: &BodyState, b: &BodyState) -> (DVec2, DVec2) {
+    let r = a.position - b.position;
+    let v = a.velocity - b.velocity;
+    let distance = r.length();
+    let rv = r.dot(v);
+    let v2 = v.length_squared();
+
+    // relative position and velocity only flip sign when looking from the other body
+    let correction =
+        |mu: f64| mu / (C * C * distance.powi(3)) * ((4.0 * mu / distance - v2) * r + 4.0 * rv * v);
+
+    (correction(G * b.mass), -correction(G * a.mass))
+}
+
+/// Same as [`velocity_verlet`], but for bodies pulling on each other.
+pub fn velocity_verlet_n_body(dt: f64, bodies: &mut [BodyState], forces: ForceModel) {
+    let a1 = compute_accelerations(bodies, forces);
+    for (body, a1) in bodies.iter_mut().zip(&a1) {
+        body.position += body.velocity * dt + 0.5 * a1 * dt * dt;
+        // the relativistic term depends on velocity, so the second acceleration is taken with
+        // a predicted one
+        body.velocity += a1 * dt;
+    }
+
+    let a2 = compute_accelerations(bodies, forces);
+    for ((body, a1), a2) in bodies.iter_mut().zip(a1).zip(a2) {
+        body.velocity += 0.5 * (a2 - a1) * dt;
+    }
+}
+
+/// Fourth order symplectic step made of three velocity Verlet steps, the middle one going
+/// backwards in time (Yoshida 1990).
+pub fn yoshida_n_body(dt: f64, bodies: &mut [BodyState], forces: ForceModel) {
+    let w1 = 1.0 / (2.0 - 2f64.cbrt());
+    let w0 = -2f64.cbrt() * w1;
+
+    for weight in [w1, w0, w1] {
+        velocity_verlet_n_body(weight * dt, bodies, forces);
+    }
+}
+
+/// Classic fourth order Runge-Kutta step. It's accurate, but not symplectic, so the energy
+/// slowly drifts over many orbits.
+pub fn runge_kutta_n_body(dt: f64, bodies: &mut [BodyState], forces: ForceModel) {
+    // derivative of every body's position and velocity
+    let derivative = |states: &[BodyState]| {
+        compute_accelerations(states, forces)
+            .into_iter()
+            .zip(states)
+            .map(|(acceleration, state)| (state.velocity, acceleration))
+            .collect::<Vec<_>>()
+    };
+    let offset = |k: &[(DVec2, DVec2)], h: f64| {
+        bodies
+            .iter()
+            .zip(k)
+            .map(|(body, (velocity, acceleration))| BodyState {
+                mass: body.mass,
+                position: body.position + velocity * h,
+                velocity: body.velocity + acceleration * h,
+            })
+            .collect::<Vec<_>>()
     };
 
-    DISTANCE_SCALE * scale
+    let k1 = derivative(bodies);
+    let k2 = derivative(&offset(&k1, 0.5 * dt));
+    let k3 = derivative(&offset(&k2, 0.5 * dt));
+    let k4 = derivative(&offset(&k3, dt));
+
+    for (i, body) in bodies.iter_mut().enumerate() {
+        body.position += dt / 6.0 * (k1[i].0 + 2.0 * k2[i].0 + 2.0 * k3[i].0 + k4[i].0);
+        body.velocity += dt / 6.0 * (k1[i].1 + 2.0 * k2[i].1 + 2.0 * k3[i].1 + k4[i].1);
+    }
+}
+
+/// Scheme the n-body system is advanced with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Integrator {
+    #[default]
+    VelocityVerlet,
+    Yoshida,
+    RungeKutta,
+}
+
+impl Integrator {
+    pub const ALL: [Self; 3] = [Self::VelocityVerlet, Self::Yoshida, Self::RungeKutta];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::VelocityVerlet => "Velocity Verlet",
+            Self::Yoshida => "Yoshida (4th order)",
+            Self::RungeKutta => "Runge-Kutta 4",
+        }
+    }
+
+    pub fn step(self, dt: f64, bodies: &mut [BodyState], forces: ForceModel) {
+        match self {
+            Self::VelocityVerlet => velocity_verlet_n_body(dt, bodies, forces),
+            Self::Yoshida => yoshida_n_body(dt, bodies, forces),
+            Self::RungeKutta => runge_kutta_n_body(dt, bodies, forces),
+        }
+    }
+}
+
+impl FromStr for Integrator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "verlet" => Ok(Self::VelocityVerlet),
+            "yoshida" => Ok(Self::Yoshida),
+            "rk4" => Ok(Self::RungeKutta),
+            _ => Err(format!(
+                "unknown integrator {name}, expected verlet, yoshida or rk4"
+            )),
+        }
+    }
+}
+
+/// Advances all bodies by `dt`, split into as many steps as needed to not exceed `MAX_TIME_STEP`.
+pub fn step_bodies(dt: f64, bodies: &mut [BodyState], forces: ForceModel, integrator: Integrator) {
+    let (steps, step) = substeps(dt);
+    for _ in 0..steps {
+        encounter_step(step, bodies, forces, integrator, |_, _| {});
+    }
+}
+
+/// Same as [`step_bodies`], but keeps where the bodies were after every substep, the shorter
+/// ones of close encounters included, so test particles can follow them afterwards.
+pub fn track_bodies(
+    dt: f64,
+    bodies: &mut [BodyState],
+    forces: ForceModel,
+    integrator: Integrator,
+) -> BodyTrack {
+    let (steps, step) = substeps(dt);
+    let mut track = BodyTrack::new(bodies);
+    for _ in 0..steps {
+        encounter_step(step, bodies, forces, integrator, |step, bodies| {
+            track.push(step, bodies)
+        });
+    }
+    track
+}
+
+fn substeps(dt: f64) -> (usize, f64) {
+    let steps = (dt.abs() / MAX_TIME_STEP).ceil().max(1.0);
+    (steps as usize, dt / steps)
+}
+
+/// Advances all bodies by exactly `dt`. With close encounter handling on, that's done in as
+/// many shorter steps as the closest pair needs, otherwise in a single one. `on_step` gets the
+/// length of every step and the bodies after it. Only the bodies decide how long the steps are,
+/// test particles coming close to one are stuck with them.
+pub fn encounter_step(
+    dt: f64,
+    bodies: &mut [BodyState],
+    forces: ForceModel,
+    integrator: Integrator,
+    mut on_step: impl FnMut(f64, &[BodyState]),
+) {
+    if !forces.close_encounters {
+        integrator.step(dt, bodies, forces);
+        on_step(dt, bodies);
+        return;
+    }
+
+    // the step is picked again every time, so it grows back once the bodies move apart
+    let mut remaining = dt;
+    while remaining != 0.0 {
+        let limit =
+            (ENCOUNTER_STEP_FRACTION * dynamical_time(bodies, forces)).max(MIN_ENCOUNTER_STEP);
+        let step = encounter_substep(dt, remaining, limit);
+        integrator.step(step, bodies, forces);
+        on_step(step, bodies);
+        remaining -= step;
+    }
+}
+
+/// Next step to take with `remaining` of `dt` left, at most `limit` long. Steps are `dt` halved
+/// as often as needed, so none of them round and they add up to exactly `dt`.
+fn encounter_substep(dt: f64, remaining: f64, limit: f64) -> f64 {
+    let mut step = dt;
+    while step.abs() > limit {
+        step /= 2.0;
+    }
+    if step.abs() > remaining.abs() {
+        remaining
+    } else {
+        step
+    }
+}
+
+/// Shortest time scale on which any body in an encounter changes noticeably. Only the body
+/// pulling hardest on each body can be in an encounter with it, so a moon isn't taken to
+/// plunge into the sun while its planet drags it along. That body is looked up in a Barnes-Hut
+/// tree, which keeps this at O(n log n) like the accelerations.
+fn dynamical_time(bodies: &[BodyState], forces: ForceModel) -> f64 {
+    let tree = QuadTree::new(bodies);
+    let softening2 = forces.softening * forces.softening;
+
+    let mut shortest = f64::INFINITY;
+    for (i, body) in bodies.iter().enumerate() {
+        if let Some(j) = tree.strongest_at(bodies, body.position, Some(i), forces.softening) {
+            shortest = shortest.min(encounter_time(body, &bodies[j], softening2));
+        }
+    }
+    shortest
+}
+
+/// Time scale of two bodies flying past each other close enough to bend their paths, the time to
+/// fall together or to pass, or of two bodies on an eccentric orbit around each other, the time
+/// to swing around the periapsis. Moons on round orbits don't hold up the whole system. Bodies
+/// within the softening length are always in an encounter.
+fn encounter_time(a: &BodyState, b: &BodyState, softening2: f64) -> f64 {
+    let d = b.position - a.position;
+    let v = b.velocity - a.velocity;
+    let distance2 = d.length_squared();
+    let r2 = distance2 + softening2;
+    let mass = a.mass + b.mass;
+    let mu = G * mass;
+    let v2 = v.length_squared();
+
+    // potential against kinetic energy, from half on the pair is bound
+    let focusing = mu / (r2.sqrt() * v2);
+    let fall = |r2: f64| (r2 * r2.sqrt() / mu).sqrt();
+
+    if distance2 < softening2 {
+        return fall(r2);
+    }
+    if focusing >= 0.5 {
+        let eccentricity = eccentricity_vector(d, v, mass).length();
+        if eccentricity >= ENCOUNTER_ECCENTRICITY {
+            let periapsis = semi_major_axis(d, v, mass) * (1.0 - eccentricity);
+            return fall(periapsis * periapsis + softening2);
+        }
+    } else if focusing >= ENCOUNTER_FOCUSING {
+        return fall(r2).min((r2 / v2).sqrt());
+    }
+    f64::INFINITY
+}
+
+/// Semi-major axis of a two-body orbit from the vis-viva equation, negative for unbound ones.
+pub fn semi_major_axis(pos: DVec2, vel: DVec2, central_mass: f64) -> f64 {
+    let mu = G * central_mass;
+    1.0 / (2.0 / pos.length() - vel.length_squared() / mu)
 }
 
-pub fn scale_distance_to_bevy(pos: DVec2) -> Vec2 {
-    let r = (pos.x * pos.x + pos.y * pos.y).sqrt();
-    let scale = compute_planet_distance_scale(r);
-    (pos / scale).as_vec2()
+/// Period of a bound two-body orbit.
+pub fn orbital_period(pos: DVec2, vel: DVec2, central_mass: f64) -> f64 {
+    let mu = G * central_mass;
+    TAU * (semi_major_axis(pos, vel, central_mass).powi(3) / mu).sqrt()
+}
+
+/// Points from the central body towards the periapsis, its length is the eccentricity.
+pub fn eccentricity_vector(pos: DVec2, vel: DVec2, central_mass: f64) -> DVec2 {
+    let mu = G * central_mass;
+    ((vel.length_squared() - mu / pos.length()) * pos - pos.dot(vel) * vel) / mu
+}
+
+/// Advance of the periapsis per revolution predicted by general relativity, in radians.
+pub fn relativistic_precession(pos: DVec2, vel: DVec2, central_mass: f64) -> f64 {
+    let mu = G * central_mass;
+    let semi_major_axis = semi_major_axis(pos, vel, central_mass);
+    let eccentricity = eccentricity_vector(pos, vel, central_mass).length();
+
+    6.0 * PI * mu / (C * C * semi_major_axis * (1.0 - eccentricity * eccentricity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encounter_steps_add_up_to_exactly_dt() {
+        for dt in [3600.0, -3600.0, 1000.0 / 3.0, 0.1] {
+            // limits that don't divide the step evenly, changing from step to step
+            let mut limits = [487.3, 1.0, 13.7, 2999.9, 0.7].into_iter().cycle();
+
+            let (mut remaining, mut elapsed) = (dt, 0.0);
+            while remaining != 0.0 {
+                let limit = limits.next().unwrap();
+                let step = encounter_substep(dt, remaining, limit);
+                assert!(step.abs() <= limit);
+                elapsed += step;
+                remaining -= step;
+            }
+            assert_eq!(elapsed, dt);
+        }
+    }
 }
@@ -0,0 +1,119 @@
+use bevy_math::{DVec2, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{DISTANCE_SCALE, INNERMOST_SATELLITE_RADII, SATELLITE_COMPRESSION};
+
+const A: f64 = 7e-25;
+const CUT_OUT: f64 = 249261000000.0;
+const MIN_VALUE: f64 = 1.0;
+const EXPONENT: f64 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ScalingMode {
+    Linear,
+    Logarithmic,
+    Compressed,
+}
+
+/// Mapping from meters to world units. Parameters of every mode are kept, so switching back and
+/// forth doesn't lose them.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DistanceScaling {
+    pub mode: ScalingMode,
+    pub true_scale_radii: bool, // scale meshes so bodies have their real size in the current mode
+    // linear
+    pub meters_per_unit: f64,
+    // logarithmic, flat near the sun and growing by `log_units` every time the distance is
+    // multiplied by e
+    pub log_reference: f64,
+    pub log_units: f64,
+    // compressed, power law that only kicks in after `cut_out`
+    pub a: f64,
+    pub cut_out: f64,
+    pub exponent: f64,
+    pub distance_scale: f64,
+}
+
+impl Default for DistanceScaling {
+    fn default() -> Self {
+        Self {
+            mode: ScalingMode::Compressed,
+            true_scale_radii: false,
+            meters_per_unit: 1e10,
+            log_reference: 5e10,
+            log_units: 60.0,
+            a: A,
+            cut_out: CUT_OUT,
+            exponent: EXPONENT,
+            distance_scale: DISTANCE_SCALE,
+        }
+    }
+}
+
+impl DistanceScaling {
+    /// Rendered distance from the sun, for a distance `r` in meters.
+    pub fn radial(&self, r: f64) -> f64 {
+        match self.mode {
+            ScalingMode::Linear => r / self.meters_per_unit,
+            ScalingMode::Logarithmic => self.log_units * (r / self.log_reference).ln_1p(),
+            ScalingMode::Compressed => r / self.compute_planet_distance_scale(r),
+        }
+    }
+
+    pub fn scale_distance_to_bevy(&self, pos: DVec2) -> Vec2 {
+        let r = (pos.x * pos.x + pos.y * pos.y).sqrt();
+        if r == 0.0 {
+            return Vec2::ZERO;
+        }
+
+        (pos / r * self.radial(r)).as_vec2()
+    }
+
+    /// Maps the position of a moon relative to its parent. Apart from true scale, the scale is
+    /// local to the moon system, so moons stay outside of their parent's mesh no matter how the
+    /// parent itself was scaled.
+    pub fn scale_satellite_distance_to_bevy(
+        &self,
+        pos: DVec2,
+        innermost_distance: f64,
+        parent_radius: f32,
+    ) -> Vec2 {
+        if self.mode == ScalingMode::Linear {
+            return self.scale_distance_to_bevy(pos);
+        }
+
+        let r = pos.length();
+        if r == 0.0 {
+            return Vec2::ZERO;
+        }
+
+        let rendered = INNERMOST_SATELLITE_RADII
+            * parent_radius as f64
+            * (r / innermost_distance).powf(SATELLITE_COMPRESSION);
+        (pos / r * rendered).as_vec2()
+    }
+
+    /// Rendered radius of a body `distance` meters away from the sun.
+    pub fn radius(&self, render_radius: f32, physical_radius: f64, distance: f64) -> f32 {
+        if !self.true_scale_radii {
+            return render_radius;
+        }
+
+        // how many meters one world unit spans around the body, the sun sits where every mode
+        // is at its flattest
+        let distance = distance.max(1.0);
+        (physical_radius * self.radial(distance) / distance) as f32
+    }
+
+    fn compute_planet_distance_scale(&self, total_dist: f64) -> f64 {
+        let c = -(self.a * self.cut_out * self.cut_out);
+
+        let scale = if total_dist > -self.cut_out && total_dist < self.cut_out {
+            MIN_VALUE
+        } else {
+            ((self.a * total_dist * total_dist + c) + MIN_VALUE).powf(self.exponent)
+        };
+
+        self.distance_scale * scale
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::{math::DVec2, prelude::*};
+
+use sun_orbit_simulation::math::particles::ParticleState;
+
+use crate::{
+    frame::{RenderFrame, update_render_frame},
+    planet::InitialScenario,
+    scaling::DistanceScale,
+};
+
+const PARTICLE_RADIUS: f32 = 0.05; // world units, they're all drawn the same size
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_particles).add_systems(
+            Update,
+            update_particle_transforms.after(update_render_frame),
+        );
+    }
+}
+
+/// Massless body like an asteroid: the planets pull on it, but it doesn't pull on anything.
+/// Checkpoints and saves hold test particles in the order they were spawned in, they have no
+/// names to tell them apart.
+#[derive(Component)]
+pub struct TestParticle {
+    pub position: DVec2,
+    pub previous_position: DVec2, // used for visual lerping
+    pub velocity: DVec2,
+}
+
+impl TestParticle {
+    pub fn new(state: ParticleState) -> Self {
+        Self {
+            position: state.position,
+            previous_position: state.position,
+            velocity: state.velocity,
+        }
+    }
+
+    pub fn state(&self) -> ParticleState {
+        ParticleState {
+            position: self.position,
+            velocity: self.velocity,
+        }
+    }
+}
+
+pub type TestParticles<'w, 's> = Query<'w, 's, &'static mut TestParticle>;
+
+fn spawn_particles(
+    mut cmds: Commands,
+    scenario: Res<InitialScenario>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if scenario.particles.is_empty() {
+        return;
+    }
+
+    // shared by all of them, so they're drawn in a few instanced batches
+    let mesh = meshes.add(Sphere::new(PARTICLE_RADIUS).mesh().ico(1).unwrap());
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.6, 0.55, 0.5),
+        unlit: true,
+        ..Default::default()
+    });
+
+    let particles = scenario
+        .particles
+        .iter()
+        .map(|state| {
+            (
+                TestParticle::new(*state),
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material.clone()),
+                Transform::default(),
+            )
+        })
+        .collect::<Vec<_>>();
+    cmds.spawn_batch(particles);
+}
+
+fn update_particle_transforms(
+    fixed_time: Res<Time<Fixed>>,
+    scale: Res<DistanceScale>,
+    frame: Res<RenderFrame>,
+    mut particles: Query<(&mut Transform, &TestParticle)>,
+) {
+    let alpha = fixed_time.overstep_fraction_f64();
+
+    particles
+        .par_iter_mut()
+        .for_each(|(mut transform, particle)| {
+            let position = frame
+                .0
+                .apply(particle.previous_position.lerp(particle.position, alpha));
+            let translation = scale.scale_distance_to_bevy(position);
+
+            transform.translation.x = translation.x;
+            transform.translation.z = translation.y;
+        });
+}
@@ -1,22 +1,143 @@
+use std::f64::consts::TAU;
+
 use bevy::{math::DVec2, prelude::*};
 
+use sun_orbit_simulation::{
+    math::{
+        calendar::{J2000, SECONDS_PER_DAY},
+        physics::{BodyState, ForceModel, Integrator, step_bodies, track_bodies},
+    },
+    scenario::{BodyConfig, Scenario},
+};
+
 use crate::{
-    math::physics::{scale_distance_to_bevy, velocity_verlet},
+    appearance::Appearance,
+    frame::{RenderFrame, update_render_frame},
+    particle::TestParticles,
+    recorder::Recorder,
+    scaling::DistanceScale,
+    sun::Sun,
+    trail::Trail,
     ui::egui::Gui,
 };
 
+const JUMP_STEP: f64 = 20.0 * 86_400.0; // simulated seconds per physics tick while jumping
+
 #[derive(Clone, Component)]
 pub struct Planet {
     pub mass: f64,
     pub position: DVec2,
     pub previous_position: DVec2, // used for visual lerping
     pub velocity: DVec2,
-    pub orbit_points: Vec<Vec3>,
+    pub orbit_points: Vec<DVec2>, // in meters, relative to the parent for moons
+    pub render_radius: f32,       // radius of the mesh in world units, not used for physics
+    pub physical_radius: f64,
+}
+
+impl Planet {
+    pub fn new(config: &BodyConfig, render_radius: f32, position: DVec2, velocity: DVec2) -> Self {
+        Self {
+            mass: config.mass,
+            position,
+            previous_position: position,
+            velocity,
+            orbit_points: vec![],
+            render_radius,
+            physical_radius: config.physical_radius,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Planet {
+    /// Body that's only simulated, large enough to be easy to collide with.
+    pub fn bare(mass: f64, position: DVec2, velocity: DVec2) -> Self {
+        Self {
+            mass,
+            position,
+            previous_position: position,
+            velocity,
+            orbit_points: vec![],
+            render_radius: 1.0,
+            physical_radius: 1e8,
+        }
+    }
+}
+
+/// Marks a moon, its position is still absolute but it's rendered around its parent.
+#[derive(Clone, Component)]
+pub struct Satellite {
+    pub parent: Entity,
+    pub innermost_distance: f64, // of all moons sharing the parent, sets the system's scale
+}
+
+/// Spin of a body around its tilted axis, the axis itself stays fixed in space.
+#[derive(Component)]
+pub struct AxialRotation {
+    pub obliquity: f32, // radians
+    pub period: f64,    // sidereal, in seconds
+}
+
+impl AxialRotation {
+    pub fn new(obliquity_degrees: f64, period: f64) -> Self {
+        Self {
+            obliquity: obliquity_degrees.to_radians() as f32,
+            period,
+        }
+    }
+
+    pub fn rotation_at(&self, time: f64) -> Quat {
+        // orbits go from +x towards +z, so spinning the same way is turning clockwise around +y
+        let angle = (time / self.period).fract() * TAU;
+        Quat::from_rotation_z(self.obliquity) * Quat::from_rotation_y(-angle as f32)
+    }
+}
+
+/// Scenario the app was started with, the bodies and test particles are spawned from it.
+#[derive(Resource, Deref)]
+pub struct InitialScenario(pub Scenario);
+
+/// Simulation clock, seconds elapsed since the scenario's epoch.
+#[derive(Resource)]
+pub struct SimulationTime {
+    pub epoch: f64, // julian date the scenario starts at
+    pub elapsed: f64,
+    pub previous_elapsed: f64,   // used for visual lerping
+    pub target: Option<f64>,     // elapsed time being jumped to
+    pub step: bool,              // advance a single tick while paused
+    pub fixed_step: Option<f64>, // deterministic mode, simulated seconds per tick
+}
+
+impl Default for SimulationTime {
+    fn default() -> Self {
+        Self {
+            epoch: J2000,
+            elapsed: 0.0,
+            previous_elapsed: 0.0,
+            target: None,
+            step: false,
+            fixed_step: None,
+        }
+    }
+}
+
+impl SimulationTime {
+    pub fn julian_date(&self) -> f64 {
+        self.epoch + self.elapsed / SECONDS_PER_DAY
+    }
+
+    /// The bodies can only get there by being simulated, so it takes a while for far away dates.
+    pub fn jump_to(&mut self, julian_date: f64) {
+        self.target = Some((julian_date - self.epoch) * SECONDS_PER_DAY);
+    }
 }
 
 #[derive(Bundle)]
 pub struct PlanetBundle {
+    pub name: Name,
     pub planet: Planet,
+    pub rotation: AxialRotation,
+    pub trail: Trail,
     pub mesh: Mesh3d,
     pub material: MeshMaterial3d<StandardMaterial>,
     pub transform: Transform,
@@ -27,25 +148,25 @@ impl PlanetBundle {
         asset_server: &Res<AssetServer>,
         meshes: &mut Assets<Mesh>,
         materials: &mut Assets<StandardMaterial>,
-        mass: f64,
-        position: DVec2,
+        config: &BodyConfig,
+        appearance: &Appearance,
+        position: DVec2, // absolute, unlike the one in config
         velocity: DVec2,
-        radius: f32,
-        texture_path: &str,
     ) -> Self {
-        let texture_handle = asset_server.load(texture_path.to_string());
+        let texture_handle = appearance
+            .texture
+            .as_ref()
+            .map(|path| asset_server.load(path.clone()));
 
         Self {
-            planet: Planet {
-                mass,
-                position,
-                previous_position: position,
-                velocity,
-                orbit_points: vec![],
-            },
-            mesh: Mesh3d(meshes.add(Sphere::new(radius))),
+            name: Name::new(config.name.clone()),
+            planet: Planet::new(config, appearance.radius, position, velocity),
+            rotation: AxialRotation::new(config.obliquity, config.rotation_period),
+            trail: Trail::default(),
+            mesh: Mesh3d(meshes.add(Sphere::new(appearance.radius))),
             material: MeshMaterial3d(materials.add(StandardMaterial {
-                base_color_texture: Some(texture_handle),
+                base_color: appearance.color,
+                base_color_texture: texture_handle,
                 ..Default::default()
             })),
             transform: Transform::default().with_translation(Vec3::new(
@@ -62,36 +183,309 @@ pub struct PlanetPlugin;
 impl Plugin for PlanetPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(Time::<Fixed>::from_hz(90.0))
+            .init_resource::<SimulationTime>()
             .add_systems(FixedUpdate, update_planet_physics)
-            .add_systems(Update, update_planet_transforms);
+            .add_systems(
+                Update,
+                (
+                    update_planet_transforms.after(update_render_frame),
+                    update_axial_rotations,
+                ),
+            );
     }
 }
 
-fn update_planet_physics(settings: Res<Gui>, time: Res<Time<Fixed>>, planets: Query<&mut Planet>) {
-    let time_scale = settings.time_scale.to_seconds();
+pub type PhysicsBodies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Name,
+        &'static mut Planet,
+        Option<&'static Satellite>,
+        Has<Sun>,
+    ),
+>;
 
-    for mut planet in planets {
-        planet.previous_position = planet.position;
+pub fn update_planet_physics(
+    settings: Res<Gui>,
+    time: Res<Time<Fixed>>,
+    mut sim_time: ResMut<SimulationTime>,
+    mut recorder: ResMut<Recorder>,
+    mut planets: PhysicsBodies,
+    mut particles: TestParticles,
+) {
+    let dt = match sim_time.target {
+        // jumps are simulated in chunks, so the app stays responsive
+        Some(target) => {
+            let remaining = target - sim_time.elapsed;
+            if remaining.abs() <= JUMP_STEP {
+                sim_time.target = None;
+            }
+            remaining.clamp(-JUMP_STEP, JUMP_STEP)
+        }
+        None if settings.time.paused && !sim_time.step => 0.0,
+        // ticks always advance the same amount, no matter how long they took in real time
+        None => match sim_time.fixed_step {
+            Some(step) if settings.time.reverse => -step,
+            Some(step) => step,
+            None => time.delta_secs_f64() * settings.time.signed_rate(),
+        },
+    };
+    sim_time.step = false;
+
+    let forces = ForceModel {
+        relativity: settings.relativity,
+        softening: settings.softening,
+        close_encounters: settings.close_encounters,
+        solver: settings.solver,
+    };
+    advance_bodies(
+        dt,
+        forces,
+        settings.integrator,
+        &mut sim_time,
+        &mut recorder,
+        &mut planets,
+        &mut particles,
+    );
+}
 
-        let dt = time.delta_secs_f64() * time_scale;
-        let (pos_new, vel_new) = velocity_verlet(dt, planet.position, planet.velocity, planet.mass);
-        planet.position = pos_new;
-        planet.velocity = vel_new;
+/// Advances the clock and every body by `dt` simulated seconds, taking any samples due on the
+/// way.
+pub fn advance_bodies(
+    dt: f64,
+    forces: ForceModel,
+    integrator: Integrator,
+    sim_time: &mut SimulationTime,
+    recorder: &mut Recorder,
+    planets: &mut PhysicsBodies,
+    particles: &mut TestParticles,
+) {
+    sim_time.previous_elapsed = sim_time.elapsed;
+    sim_time.elapsed += dt;
+
+    // bodies are integrated in the order they were spawned, like in the library's simulation,
+    // the query's own order depends on which components they have
+    let mut states = planets
+        .iter()
+        .sort_by_key::<Entity, _>(|entity| entity.index())
+        .map(|(_, _, planet, ..)| BodyState {
+            mass: planet.mass,
+            position: planet.position,
+            velocity: planet.velocity,
+        })
+        .collect::<Vec<_>>();
+
+    // a recording starts with the state it was started at, even while paused
+    if recorder.take_initial_sample() {
+        let time = sim_time.previous_elapsed;
+        recorder.record(
+            sim_time.epoch + time / SECONDS_PER_DAY,
+            time,
+            &orbited_bodies(planets),
+            &states,
+        );
+    }
+
+    // nothing to integrate while paused, the bodies just have to stop being interpolated
+    if dt == 0.0 {
+        for (_, _, mut planet, ..) in planets.iter_mut() {
+            planet.previous_position = planet.position;
+        }
+        for mut particle in particles.iter_mut() {
+            particle.previous_position = particle.position;
+        }
+        return;
+    }
+
+    for mut particle in particles.iter_mut() {
+        particle.previous_position = particle.position;
+    }
+
+    // test particles follow the bodies afterwards, all at once
+    if particles.is_empty() {
+        step_bodies(dt, &mut states, forces, integrator);
+    } else {
+        let track = track_bodies(dt, &mut states, forces, integrator);
+        particles.par_iter_mut().for_each(|mut particle| {
+            let mut state = particle.state();
+            track.advance(&mut state, forces.softening);
+            particle.position = state.position;
+            particle.velocity = state.velocity;
+        });
+    }
+
+    // the tick isn't split at sample times, that would make recorded runs move differently, the
+    // sample is taken where the tick reaching it ended
+    if recorder
+        .next_sample(sim_time.previous_elapsed, sim_time.elapsed)
+        .is_some()
+    {
+        let time = sim_time.elapsed;
+        recorder.record(
+            sim_time.epoch + time / SECONDS_PER_DAY,
+            time,
+            &orbited_bodies(planets),
+            &states,
+        );
+    }
+
+    for ((_, _, mut planet, ..), state) in planets
+        .iter_mut()
+        .sort_by_key::<Entity, _>(|entity| entity.index())
+        .zip(states)
+    {
+        planet.previous_position = planet.position;
+        planet.position = state.position;
+        planet.velocity = state.velocity;
     }
 }
 
-fn update_planet_transforms(
+/// Name of every body in spawn order and the index of the one it orbits, moons orbit their
+/// parent and everything else the sun.
+pub fn orbited_bodies<'a>(planets: &'a PhysicsBodies) -> Vec<(&'a str, Option<usize>)> {
+    let mut entities = planets
+        .iter()
+        .map(|(entity, ..)| entity)
+        .collect::<Vec<_>>();
+    entities.sort_unstable_by_key(|entity| entity.index());
+    let sun = planets
+        .iter()
+        .find(|(.., is_sun)| *is_sun)
+        .map(|(entity, ..)| entity);
+
+    planets
+        .iter()
+        .sort_by_key::<Entity, _>(|entity| entity.index())
+        .map(|(_, name, _, satellite, is_sun)| {
+            let central = match satellite {
+                Some(satellite) => Some(satellite.parent),
+                None if is_sun => None,
+                None => sun,
+            };
+            let central = central.and_then(|central| entities.iter().position(|e| *e == central));
+            (name.as_str(), central)
+        })
+        .collect()
+}
+
+pub fn update_planet_transforms(
     fixed_time: Res<Time<Fixed>>,
-    planets: Query<(&mut Transform, &Planet)>,
+    scale: Res<DistanceScale>,
+    frame: Res<RenderFrame>,
+    mut planets: Query<(&mut Transform, &Planet, Option<&Satellite>)>,
+    parents: Query<&Planet>,
 ) {
-    for (mut transform, planet) in planets {
-        let interpolated = scale_distance_to_bevy(
-            planet
-                .previous_position
-                .lerp(planet.position, fixed_time.overstep_fraction_f64()),
+    let alpha = fixed_time.overstep_fraction_f64();
+
+    for (mut transform, planet, satellite) in &mut planets {
+        let position = frame
+            .0
+            .apply(planet.previous_position.lerp(planet.position, alpha));
+
+        // moons are placed around their parent's rendered position, otherwise the non-linear
+        // scaling would squash them into the parent
+        let parent = satellite.and_then(|s| Some((s, parents.get(s.parent).ok()?)));
+        let interpolated = match parent {
+            Some((satellite, parent)) => {
+                let parent_position = frame
+                    .0
+                    .apply(parent.previous_position.lerp(parent.position, alpha));
+                let parent_radius = scale.radius(
+                    parent.render_radius,
+                    parent.physical_radius,
+                    parent_position.length(),
+                );
+
+                scale.scale_distance_to_bevy(parent_position)
+                    + scale.scale_satellite_distance_to_bevy(
+                        position - parent_position,
+                        satellite.innermost_distance,
+                        parent_radius,
+                    )
+            }
+            None => scale.scale_distance_to_bevy(position),
+        };
+
+        let radius = scale.radius(
+            planet.render_radius,
+            planet.physical_radius,
+            position.length(),
         );
 
         transform.translation.x = interpolated.x;
         transform.translation.z = interpolated.y;
+        transform.scale = Vec3::splat(radius / planet.render_radius);
+    }
+}
+
+fn update_axial_rotations(
+    fixed_time: Res<Time<Fixed>>,
+    sim_time: Res<SimulationTime>,
+    bodies: Query<(&mut Transform, &AxialRotation)>,
+) {
+    let time = sim_time.previous_elapsed
+        + (sim_time.elapsed - sim_time.previous_elapsed) * fixed_time.overstep_fraction_f64();
+
+    for (mut transform, rotation) in bodies {
+        transform.rotation = rotation.rotation_at(time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sun_orbit_simulation::{
+        constants::{IO_MASS, IO_POS_X, IO_VEL_Y, JUPITER_MASS},
+        math::physics::orbital_period,
+    };
+
+    use super::*;
+
+    // physics happens in the x-z plane of the world
+    fn world(position: DVec2) -> Vec3 {
+        Vec3::new(position.x as f32, 0.0, position.y as f32)
+    }
+
+    #[test]
+    fn tidally_locked_moons_keep_facing_their_planet() {
+        let mut bodies = [
+            BodyState {
+                mass: JUPITER_MASS,
+                position: DVec2::ZERO,
+                velocity: DVec2::ZERO,
+            },
+            BodyState {
+                mass: IO_MASS,
+                position: DVec2::new(IO_POS_X, 0.0),
+                velocity: DVec2::new(0.0, IO_VEL_Y),
+            },
+        ];
+        let period = orbital_period(
+            bodies[1].position,
+            bodies[1].velocity,
+            JUPITER_MASS + IO_MASS,
+        );
+        let rotation = AxialRotation::new(0.0, period);
+
+        // the side of the moon facing the planet at the start
+        let facing =
+            |bodies: &[BodyState]| world(bodies[0].position - bodies[1].position).normalize();
+        let near_side = rotation.rotation_at(0.0).inverse() * facing(&bodies);
+
+        let step = period / 8.0;
+        for i in 1..=8 {
+            step_bodies(
+                step,
+                &mut bodies,
+                ForceModel::default(),
+                Integrator::Yoshida,
+            );
+            let turned = rotation.rotation_at(i as f64 * step) * near_side;
+            assert!(
+                turned.angle_between(facing(&bodies)) < 0.02,
+                "the near side turned away {i}/8 of an orbit in"
+            );
+        }
     }
 }
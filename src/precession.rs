@@ -0,0 +1,155 @@
+use std::f64::consts::{PI, TAU};
+
+use bevy::prelude::*;
+
+use sun_orbit_simulation::math::physics::{
+    eccentricity_vector, orbital_period, relativistic_precession,
+};
+
+use crate::{
+    planet::{Planet, Satellite, SimulationTime, update_planet_physics},
+    sun::Sun,
+    ui::egui::Gui,
+};
+
+const CENTURY: f64 = 100.0 * 365.25 * 86_400.0; // julian, in seconds
+const ARCSECONDS_PER_RADIAN: f64 = 180.0 / PI * 3600.0;
+
+pub struct PrecessionPlugin;
+
+impl Plugin for PrecessionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrecessionMeter>()
+            .add_systems(FixedUpdate, measure_precession.after(update_planet_physics));
+    }
+}
+
+/// Measures how fast the periapsis of the selected body turns, by fitting a line through the
+/// direction of its eccentricity vector over time.
+///
+/// Newtonian perturbations by other bodies and the integrator's own error turn it too, so the
+/// last rate measured without relativity is kept as a baseline to compare against.
+#[derive(Resource, Default)]
+pub struct PrecessionMeter {
+    body: Option<Entity>,
+    relativity: bool,
+    baseline: Option<f64>, // arcseconds per century
+    start: Option<f64>,    // simulated time of the first sample
+    elapsed: f64,
+    previous_angle: f64,
+    angle: f64, // unwrapped, so it keeps growing past a full turn
+    // sums for the least squares fit
+    samples: f64,
+    sum_t: f64,
+    sum_angle: f64,
+    sum_tt: f64,
+    sum_t_angle: f64,
+    expected: f64, // radians per second predicted by general relativity
+}
+
+impl PrecessionMeter {
+    pub fn reset(&mut self) {
+        *self = Self {
+            body: self.body,
+            relativity: self.relativity,
+            baseline: self.baseline,
+            ..Default::default()
+        };
+    }
+
+    /// Measured precession in arcseconds per century, once there are enough samples.
+    pub fn rate(&self) -> Option<f64> {
+        let denominator = self.samples * self.sum_tt - self.sum_t * self.sum_t;
+        if self.samples < 2.0 || denominator <= 0.0 {
+            return None;
+        }
+
+        let slope = (self.samples * self.sum_t_angle - self.sum_t * self.sum_angle) / denominator;
+        Some(slope * CENTURY * ARCSECONDS_PER_RADIAN)
+    }
+
+    /// Measured precession minus the Newtonian baseline, only while relativity is on.
+    pub fn relativistic_rate(&self) -> Option<f64> {
+        if !self.relativity {
+            return None;
+        }
+
+        Some(self.rate()? - self.baseline?)
+    }
+
+    /// General relativity's share of the precession in arcseconds per century, from the latest
+    /// orbital elements.
+    pub fn expected_rate(&self) -> f64 {
+        self.expected * CENTURY * ARCSECONDS_PER_RADIAN
+    }
+
+    /// Simulated years covered by the measurement.
+    pub fn years(&self) -> f64 {
+        self.elapsed / (365.25 * 86_400.0)
+    }
+
+    fn add_sample(&mut self, time: f64, angle: f64) {
+        let start = *self.start.get_or_insert(time);
+        if self.samples == 0.0 {
+            self.previous_angle = angle;
+            self.angle = angle;
+        }
+
+        // take the shortest way around to the new angle
+        let mut delta = angle - self.previous_angle;
+        delta -= TAU * (delta / TAU).round();
+        self.angle += delta;
+        self.previous_angle = angle;
+
+        // relative to the start, the sums would lose precision over centuries otherwise
+        let t = time - start;
+        self.elapsed = t;
+        self.samples += 1.0;
+        self.sum_t += t;
+        self.sum_angle += self.angle;
+        self.sum_tt += t * t;
+        self.sum_t_angle += t * self.angle;
+    }
+}
+
+fn measure_precession(
+    settings: Res<Gui>,
+    sim_time: Res<SimulationTime>,
+    mut meter: ResMut<PrecessionMeter>,
+    bodies: Query<(&Planet, Option<&Satellite>)>,
+    sun: Single<Entity, With<Sun>>,
+) {
+    // a measurement mixing two setups is meaningless
+    if meter.body != settings.precession_body {
+        meter.body = settings.precession_body;
+        meter.baseline = None;
+        meter.reset();
+    }
+    if meter.relativity != settings.relativity {
+        if !meter.relativity {
+            meter.baseline = meter.rate().or(meter.baseline);
+        }
+        meter.relativity = settings.relativity;
+        meter.reset();
+    }
+
+    let Some(entity) = meter.body.filter(|entity| entity != &*sun) else {
+        return;
+    };
+    let Ok((planet, satellite)) = bodies.get(entity) else {
+        return;
+    };
+    let central = satellite.map_or(*sun, |s| s.parent);
+    let Ok((central, _)) = bodies.get(central) else {
+        return;
+    };
+
+    let position = planet.position - central.position;
+    let velocity = planet.velocity - central.velocity;
+    let mass = central.mass + planet.mass;
+
+    let eccentricity = eccentricity_vector(position, velocity, mass);
+    meter.add_sample(sim_time.elapsed, eccentricity.to_angle());
+    meter.expected = relativistic_precession(position, velocity, mass)
+        / orbital_period(position, velocity, mass);
+}
@@ -0,0 +1,273 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use sun_orbit_simulation::math::{
+    calendar::SECONDS_PER_DAY,
+    physics::{BodyState, eccentricity_vector, semi_major_axis},
+};
+
+const CSV_HEADER: &str =
+    "julian_date,elapsed,body,x,y,vx,vy,distance,speed,semi_major_axis,eccentricity";
+
+pub struct RecorderPlugin;
+
+impl Plugin for RecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recorder>();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordFormat {
+    Csv,
+    JsonLines,
+}
+
+impl RecordFormat {
+    /// `.csv` files get CSV, anything else JSON Lines.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Self::Csv,
+            _ => Self::JsonLines,
+        }
+    }
+}
+
+/// Writes the state of every body to a file at a fixed simulated-time interval. Samples are
+/// taken by the physics at the end of the tick reaching each multiple of the interval, so they
+/// land exactly on it when the ticks do, like in headless runs with the default interval.
+#[derive(Resource)]
+pub struct Recorder {
+    pub interval: f64, // simulated seconds between samples
+    output: Option<Output>,
+    initial_sample: bool, // the state recording started at is still to be written
+    pub status: Option<String>, // shown in the ui
+}
+
+struct Output {
+    path: PathBuf,
+    format: RecordFormat,
+    writer: BufWriter<File>,
+}
+
+/// One body at one sample, in SI units.
+#[derive(Serialize)]
+struct Row<'a> {
+    julian_date: f64,
+    elapsed: f64,
+    body: &'a str,
+    x: f64,
+    y: f64,
+    vx: f64,
+    vy: f64,
+    // relative to the body it orbits, so they're missing for the sun
+    distance: Option<f64>,
+    speed: Option<f64>,
+    semi_major_axis: Option<f64>,
+    eccentricity: Option<f64>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self {
+            interval: SECONDS_PER_DAY,
+            output: None,
+            initial_sample: false,
+            status: None,
+        }
+    }
+}
+
+impl Recorder {
+    pub fn start(&mut self, path: PathBuf) -> io::Result<()> {
+        self.stop();
+
+        let format = RecordFormat::from_path(&path);
+        let mut writer = BufWriter::new(File::create(&path)?);
+        if format == RecordFormat::Csv {
+            writeln!(writer, "{CSV_HEADER}")?;
+        }
+
+        self.initial_sample = true;
+        self.status = Some(format!("Recording to {}", path.display()));
+        self.output = Some(Output {
+            path,
+            format,
+            writer,
+        });
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(mut output) = self.output.take() {
+            if let Err(err) = output.writer.flush() {
+                error!("Couldn't finish {}: {err}", output.path.display());
+            }
+            self.status = Some(format!("Recorded to {}", output.path.display()));
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.output.is_some()
+    }
+
+    /// Whether the state at the start of the recording still has to be written, only true once.
+    pub fn take_initial_sample(&mut self) -> bool {
+        self.is_recording() && std::mem::take(&mut self.initial_sample)
+    }
+
+    /// First sample time after `from`, up to and including `to`. Samples fall on multiples of
+    /// the interval, in whichever direction time flows.
+    pub fn next_sample(&self, from: f64, to: f64) -> Option<f64> {
+        if !self.is_recording() {
+            return None;
+        }
+        sample_after(from, to, self.interval)
+    }
+
+    /// Writes one sample, `bodies` names every state and the index of the one it orbits.
+    pub fn record(
+        &mut self,
+        julian_date: f64,
+        elapsed: f64,
+        bodies: &[(&str, Option<usize>)],
+        states: &[BodyState],
+    ) {
+        let Some(output) = &mut self.output else {
+            return;
+        };
+
+        if let Err(err) = write_sample(output, julian_date, elapsed, bodies, states) {
+            error!("Couldn't record to {}: {err}", output.path.display());
+            self.status = Some(format!("Recording stopped: {err}"));
+            self.output = None;
+        }
+    }
+}
+
+fn sample_after(from: f64, to: f64, interval: f64) -> Option<f64> {
+    if interval <= 0.0 {
+        return None;
+    }
+
+    // the division can round either way, so start a sample early and walk until it's past
+    // `from`, otherwise a rounded down product could return `from` itself forever
+    let forward = to >= from;
+    let step = if forward { 1.0 } else { -1.0 };
+    let mut index = (from / interval).round() - step;
+    let past = |sample: f64| {
+        if forward {
+            sample > from
+        } else {
+            sample < from
+        }
+    };
+    while !past(index * interval) {
+        index += step;
+    }
+
+    let sample = index * interval;
+    let reached = if forward { sample <= to } else { sample >= to };
+    reached.then_some(sample)
+}
+
+fn write_sample(
+    output: &mut Output,
+    julian_date: f64,
+    elapsed: f64,
+    bodies: &[(&str, Option<usize>)],
+    states: &[BodyState],
+) -> io::Result<()> {
+    for ((name, central), body) in bodies.iter().zip(states) {
+        let relative = central.map(|central| {
+            let central = states[central];
+            let position = body.position - central.position;
+            let velocity = body.velocity - central.velocity;
+            let mass = central.mass + body.mass;
+            (position, velocity, mass)
+        });
+
+        let row = Row {
+            julian_date,
+            elapsed,
+            body: name,
+            x: body.position.x,
+            y: body.position.y,
+            vx: body.velocity.x,
+            vy: body.velocity.y,
+            distance: relative.map(|(position, ..)| position.length()),
+            speed: relative.map(|(_, velocity, _)| velocity.length()),
+            semi_major_axis: relative.map(|(p, v, m)| semi_major_axis(p, v, m)),
+            eccentricity: relative.map(|(p, v, m)| eccentricity_vector(p, v, m).length()),
+        };
+
+        match output.format {
+            RecordFormat::Csv => write_csv_row(&mut output.writer, &row)?,
+            RecordFormat::JsonLines => {
+                serde_json::to_writer(&mut output.writer, &row)?;
+                writeln!(output.writer)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_csv_row(writer: &mut impl Write, row: &Row) -> io::Result<()> {
+    let optional = |value: Option<f64>| value.map_or(String::new(), |value| value.to_string());
+
+    // names come from the scenario, quote them in case one has a comma
+    writeln!(
+        writer,
+        "{},{},\"{}\",{},{},{},{},{},{},{},{}",
+        row.julian_date,
+        row.elapsed,
+        row.body.replace('"', "\"\""),
+        row.x,
+        row.y,
+        row.vx,
+        row.vy,
+        optional(row.distance),
+        optional(row.speed),
+        optional(row.semi_major_axis),
+        optional(row.eccentricity),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // intervals the logarithmic slider in the ui can produce, in days
+    const AWKWARD_INTERVALS: [f64; 4] = [1.906_485_993_122_075_7, 0.1, 1.0 / 3.0, 365.25];
+
+    #[test]
+    fn samples_always_move_on() {
+        for days in AWKWARD_INTERVALS {
+            let interval = days * SECONDS_PER_DAY;
+            let end = 5000.0 * interval;
+
+            let mut time = 0.0;
+            let mut samples = 0;
+            while let Some(sample) = sample_after(time, end, interval) {
+                assert!(sample > time, "stuck at {time} with {days} days");
+                time = sample;
+                samples += 1;
+            }
+            assert_eq!(samples, 5000, "skipped samples with {days} days");
+
+            while let Some(sample) = sample_after(time, 0.0, interval) {
+                assert!(sample < time, "stuck at {time} going back with {days} days");
+                time = sample;
+                samples -= 1;
+            }
+            assert_eq!(samples, 0, "skipped samples going back with {days} days");
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{appearance::RingConfig, ui::egui::Gui};
+
+const RING_SEGMENTS: u32 = 128;
+const RING_TEXTURE_WIDTH: u32 = 512;
+
+pub struct RingPlugin;
+
+impl Plugin for RingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_ring_visibility);
+    }
+}
+
+#[derive(Component)]
+pub struct Ring {
+    pub faint: bool, // hidden unless enabled in the ui
+}
+
+/// Ring system lying in the equatorial plane of its parent, so it must be spawned as a child
+/// of the body to follow its tilt.
+#[derive(Bundle)]
+pub struct RingBundle {
+    pub ring: Ring,
+    pub mesh: Mesh3d,
+    pub material: MeshMaterial3d<StandardMaterial>,
+    pub transform: Transform,
+    pub visibility: Visibility,
+}
+
+impl RingBundle {
+    pub fn new(
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+        images: &mut Assets<Image>,
+        config: &RingConfig,
+        body_radius: f32,
+    ) -> Self {
+        let texture = images.add(ring_texture(config));
+
+        Self {
+            ring: Ring {
+                faint: config.faint,
+            },
+            mesh: Mesh3d(meshes.add(ring_mesh(
+                config.inner_radius * body_radius,
+                config.outer_radius * body_radius,
+            ))),
+            material: MeshMaterial3d(materials.add(StandardMaterial {
+                base_color_texture: Some(texture),
+                alpha_mode: AlphaMode::Blend,
+                double_sided: true,
+                cull_mode: None,
+                ..Default::default()
+            })),
+            transform: Transform::default(),
+            visibility: Visibility::default(),
+        }
+    }
+}
+
+fn update_ring_visibility(settings: Res<Gui>, rings: Query<(&Ring, &mut Visibility)>) {
+    for (ring, mut visibility) in rings {
+        visibility.set_if_neq(if !ring.faint || settings.show_faint_rings {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+// flat annulus in the XZ plane, U goes from the inner to the outer edge
+fn ring_mesh(inner_radius: f32, outer_radius: f32) -> Mesh {
+    let vertex_count = 2 * (RING_SEGMENTS as usize + 1);
+    let mut positions = Vec::with_capacity(vertex_count);
+    let mut uvs = Vec::with_capacity(vertex_count);
+    let mut indices = Vec::with_capacity(6 * RING_SEGMENTS as usize);
+
+    for i in 0..=RING_SEGMENTS {
+        let (sin, cos) = (i as f32 / RING_SEGMENTS as f32 * TAU).sin_cos();
+        positions.push([inner_radius * cos, 0.0, inner_radius * sin]);
+        positions.push([outer_radius * cos, 0.0, outer_radius * sin]);
+        uvs.push([0.0, 0.5]);
+        uvs.push([1.0, 0.5]);
+    }
+
+    for i in 0..RING_SEGMENTS {
+        let inner = 2 * i;
+        indices.extend([inner, inner + 2, inner + 1, inner + 1, inner + 2, inner + 3]);
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; vertex_count])
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+// one pixel high strip sampled along the radius, there are no ring textures in the assets
+fn ring_texture(config: &RingConfig) -> Image {
+    let [r, g, b, _] = config.color.to_srgba().to_u8_array();
+
+    let data = (0..RING_TEXTURE_WIDTH)
+        .flat_map(|x| {
+            let t = (x as f32 + 0.5) / RING_TEXTURE_WIDTH as f32;
+            let radius = config.inner_radius + (config.outer_radius - config.inner_radius) * t;
+
+            let alpha = if config
+                .gaps
+                .iter()
+                .any(|(start, end)| (*start..*end).contains(&radius))
+            {
+                0.0
+            } else {
+                // fine ringlets so it doesn't look like a flat disc
+                let ringlets = 0.8 + 0.2 * (radius * 157.0).sin() * (radius * 41.0).sin();
+                config.opacity * ringlets
+            };
+
+            [r, g, b, (alpha.clamp(0.0, 1.0) * 255.0) as u8]
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: RING_TEXTURE_WIDTH,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
@@ -0,0 +1,583 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{math::DVec2, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use sun_orbit_simulation::math::{
+    particles::ParticleState,
+    physics::{GravitySolver, Integrator},
+    scaling::DistanceScaling,
+};
+
+use crate::{
+    camera::{MainCamera, PointCamera},
+    checkpoint::Checkpoints,
+    collision::{Absorbed, absorb, restore_absorbed},
+    frame::ReferenceFrame,
+    particle::TestParticle,
+    planet::{Planet, Satellite, SimulationTime},
+    precession::PrecessionMeter,
+    trail::Trail,
+    ui::egui::{Gui, TimeSettings, ViewportCorner},
+};
+
+// bump whenever the format changes in a way older versions can't read
+const SAVE_VERSION: u32 = 2; // 2 has merged bodies, 1 would bring them back
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveRequests>()
+            .add_systems(Update, (save_state, load_state));
+    }
+}
+
+/// Files to save to or load from on the next frame, set by the ui or the command line.
+#[derive(Resource, Default)]
+pub struct SaveRequests {
+    pub save: Option<PathBuf>,
+    pub load: Option<PathBuf>,
+    pub status: Option<String>, // outcome of the last request, shown in the ui
+}
+
+/// Everything needed to continue a simulation later. Bodies and settings referring to them are
+/// stored by name, test particles in the order they were spawned in. The scenario still decides
+/// how bodies look.
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    version: u32,
+    epoch: f64, // julian date
+    elapsed: f64,
+    bodies: Vec<SavedBody>,
+    #[serde(default)] // added after version 1
+    particles: Vec<SavedParticle>,
+    settings: SavedSettings,
+    camera: SavedCamera,
+}
+
+// read on its own first, so a newer format gives a sensible error instead of a parsing one
+#[derive(Deserialize)]
+struct SaveVersion {
+    version: u32,
+}
+
+/// Bodies absorbed in a collision are saved as they were when they merged.
+#[derive(Serialize, Deserialize)]
+struct SavedBody {
+    name: String,
+    mass: f64,
+    position: [f64; 2],
+    velocity: [f64; 2],
+    // added in version 2, older saves leave these as they are
+    #[serde(default)]
+    absorbed: bool,
+    #[serde(default)]
+    physical_radius: f64,
+    #[serde(default)]
+    satellite: Option<SavedSatellite>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSatellite {
+    parent: String,
+    innermost_distance: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedParticle {
+    position: [f64; 2],
+    velocity: [f64; 2],
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedSettings {
+    time: TimeSettings,
+    relativity: bool,
+    #[serde(default)] // added after version 1
+    integrator: Integrator,
+    #[serde(default)]
+    softening: f64,
+    #[serde(default)]
+    close_encounters: bool,
+    #[serde(default)]
+    solver: GravitySolver,
+    precession_body: Option<String>,
+    show_orbits: bool,
+    show_faint_rings: bool,
+    show_trails: bool,
+    distance_scaling: DistanceScaling,
+    reference_frame: ReferenceFrame,
+    frame_body: Option<String>,
+    main_focus: Option<String>,
+    overview_enabled: bool,
+    overview_focus: Option<String>,
+    overview_corner: ViewportCorner,
+    overview_size: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedCamera {
+    distance: f32,
+    pitch: f32,
+    yaw: f32,
+}
+
+// every body of the scenario, the ones absorbed in collisions too
+type SavingBodies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Name,
+        AnyOf<(&'static Planet, &'static Absorbed)>,
+        Option<&'static Satellite>,
+    ),
+>;
+pub type LoadingBodies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Name,
+        AnyOf<(&'static mut Planet, &'static Absorbed)>,
+    ),
+>;
+
+fn save_state(
+    mut requests: ResMut<SaveRequests>,
+    settings: Res<Gui>,
+    sim_time: Res<SimulationTime>,
+    camera: Single<&PointCamera, With<MainCamera>>,
+    bodies: SavingBodies,
+    particles: Query<(Entity, &TestParticle)>,
+) {
+    let Some(path) = requests.save.take() else {
+        return;
+    };
+
+    let name_of = |entity: Option<Entity>| {
+        let (_, name, ..) = bodies.get(entity?).ok()?;
+        Some(name.to_string())
+    };
+
+    let save = SaveFile {
+        version: SAVE_VERSION,
+        epoch: sim_time.epoch,
+        elapsed: sim_time.elapsed,
+        bodies: bodies
+            .iter()
+            .map(|(_, name, (planet, absorbed), satellite)| {
+                let planet = planet.or(absorbed.map(|Absorbed(planet)| planet)).unwrap();
+                SavedBody {
+                    name: name.to_string(),
+                    mass: planet.mass,
+                    position: planet.position.to_array(),
+                    velocity: planet.velocity.to_array(),
+                    absorbed: absorbed.is_some(),
+                    physical_radius: planet.physical_radius,
+                    satellite: satellite.and_then(|satellite| {
+                        Some(SavedSatellite {
+                            parent: name_of(Some(satellite.parent))?,
+                            innermost_distance: satellite.innermost_distance,
+                        })
+                    }),
+                }
+            })
+            .collect(),
+        particles: particles
+            .iter()
+            .sort_by_key::<Entity, _>(|entity| entity.index())
+            .map(|(_, particle)| SavedParticle {
+                position: particle.position.to_array(),
+                velocity: particle.velocity.to_array(),
+            })
+            .collect(),
+        settings: SavedSettings {
+            time: settings.time,
+            relativity: settings.relativity,
+            integrator: settings.integrator,
+            softening: settings.softening,
+            close_encounters: settings.close_encounters,
+            solver: settings.solver,
+            precession_body: name_of(settings.precession_body),
+            show_orbits: settings.show_orbits,
+            show_faint_rings: settings.show_faint_rings,
+            show_trails: settings.show_trails,
+            distance_scaling: settings.distance_scaling,
+            reference_frame: settings.reference_frame,
+            frame_body: name_of(settings.frame_body),
+            main_focus: name_of(settings.main_focus),
+            overview_enabled: settings.overview.enabled,
+            overview_focus: name_of(settings.overview.focus),
+            overview_corner: settings.overview.corner,
+            overview_size: settings.overview.size,
+        },
+        camera: SavedCamera {
+            distance: camera.distance,
+            pitch: camera.pitch,
+            yaw: camera.yaw,
+        },
+    };
+
+    let status = match write_save(&path, &save) {
+        Ok(()) => format!("Saved to {}", path.display()),
+        Err(err) => {
+            error!("Couldn't save to {}: {err}", path.display());
+            format!("Couldn't save: {err}")
+        }
+    };
+    requests.status = Some(status);
+}
+
+fn load_state(
+    mut cmds: Commands,
+    mut requests: ResMut<SaveRequests>,
+    (mut settings, mut camera): (ResMut<Gui>, Single<&mut PointCamera, With<MainCamera>>),
+    mut sim_time: ResMut<SimulationTime>,
+    mut bodies: LoadingBodies,
+    mut particles: Query<(Entity, &mut TestParticle)>,
+    (mut checkpoints, mut meter, mut trails): (
+        ResMut<Checkpoints>,
+        ResMut<PrecessionMeter>,
+        Query<&mut Trail>,
+    ),
+) {
+    let Some(path) = requests.load.take() else {
+        return;
+    };
+
+    let save = match read_save(&path) {
+        Ok(save) => save,
+        Err(err) => {
+            error!("Couldn't load {}: {err}", path.display());
+            requests.status = Some(format!("Couldn't load: {err}"));
+            return;
+        }
+    };
+
+    let entities = apply_simulation(&mut cmds, &save, &mut sim_time, &mut bodies, &mut particles);
+
+    let entity_of = |name: &Option<String>| entities.get(name.as_deref()?).copied();
+
+    let saved = &save.settings;
+    settings.time = saved.time;
+    settings.relativity = saved.relativity;
+    settings.integrator = saved.integrator;
+    settings.softening = saved.softening;
+    settings.close_encounters = saved.close_encounters;
+    settings.solver = saved.solver;
+    settings.precession_body = entity_of(&saved.precession_body);
+    settings.show_orbits = saved.show_orbits;
+    settings.show_faint_rings = saved.show_faint_rings;
+    settings.show_trails = saved.show_trails;
+    settings.distance_scaling = saved.distance_scaling;
+    settings.reference_frame = saved.reference_frame;
+    settings.frame_body = entity_of(&saved.frame_body);
+    settings.main_focus = entity_of(&saved.main_focus);
+    settings.overview.enabled = saved.overview_enabled;
+    settings.overview.focus = entity_of(&saved.overview_focus);
+    settings.overview.corner = saved.overview_corner;
+    settings.overview.size = saved.overview_size;
+
+    camera.distance = save.camera.distance;
+    camera.pitch = save.camera.pitch;
+    camera.yaw = save.camera.yaw;
+
+    // none of the history leads to the loaded state
+    *checkpoints = Checkpoints::default();
+    meter.reset();
+    for mut trail in &mut trails {
+        trail.clear();
+    }
+
+    requests.status = Some(format!("Loaded {}", path.display()));
+}
+
+/// Puts the bodies, test particles and time of a save into the simulation, everything but the
+/// settings and the view. Returns the entity of every body by name.
+pub fn apply_simulation(
+    cmds: &mut Commands,
+    save: &SaveFile,
+    sim_time: &mut SimulationTime,
+    bodies: &mut LoadingBodies,
+    particles: &mut Query<(Entity, &mut TestParticle)>,
+) -> HashMap<String, Entity> {
+    let entities = bodies
+        .iter()
+        .map(|(entity, name, _)| (name.to_string(), entity))
+        .collect::<HashMap<_, _>>();
+    // older saves don't know about collisions
+    let merges = save.version >= 2;
+
+    for body in &save.bodies {
+        let Some(&entity) = entities.get(&body.name) else {
+            warn!("Skipping {}, it's not part of the scenario", body.name);
+            continue;
+        };
+        let Ok((_, _, (planet, absorbed))) = bodies.get_mut(entity) else {
+            continue;
+        };
+
+        let mut loaded = match (&planet, absorbed) {
+            (Some(planet), _) => Planet::clone(planet),
+            (None, Some(Absorbed(planet))) => planet.clone(),
+            (None, None) => continue,
+        };
+        loaded.mass = body.mass;
+        loaded.position = DVec2::from_array(body.position);
+        loaded.previous_position = loaded.position;
+        loaded.velocity = DVec2::from_array(body.velocity);
+        if merges {
+            loaded.physical_radius = body.physical_radius;
+        }
+
+        // bodies merge or come back the same way as while simulating and rewinding
+        match planet {
+            _ if merges && body.absorbed => absorb(cmds, entity, loaded),
+            Some(mut planet) => *planet = loaded,
+            None => restore_absorbed(cmds, entity, loaded),
+        }
+
+        if merges {
+            let satellite = body.satellite.as_ref().and_then(|satellite| {
+                Some(Satellite {
+                    parent: *entities.get(&satellite.parent)?,
+                    innermost_distance: satellite.innermost_distance,
+                })
+            });
+            match satellite {
+                Some(satellite) => cmds.entity(entity).insert(satellite),
+                None => cmds.entity(entity).remove::<Satellite>(),
+            };
+        }
+    }
+
+    // the scenario decides how many there are, a save of another one only fills in some of them
+    let count = particles.iter().len();
+    if save.particles.len() != count {
+        warn!(
+            "The save has {} test particles, the scenario {count}",
+            save.particles.len()
+        );
+    }
+    let particles = particles
+        .iter_mut()
+        .sort_by_key::<Entity, _>(|entity| entity.index());
+    for ((_, mut particle), saved) in particles.zip(&save.particles) {
+        *particle = TestParticle::new(ParticleState {
+            position: DVec2::from_array(saved.position),
+            velocity: DVec2::from_array(saved.velocity),
+        });
+    }
+
+    sim_time.epoch = save.epoch;
+    sim_time.elapsed = save.elapsed;
+    sim_time.previous_elapsed = save.elapsed;
+    sim_time.target = None;
+
+    entities
+}
+
+fn write_save(path: &Path, save: &SaveFile) -> Result<(), Box<dyn Error>> {
+    fs::write(path, serde_json::to_string_pretty(save)?)?;
+    Ok(())
+}
+
+pub fn read_save(path: &Path) -> Result<SaveFile, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+
+    let SaveVersion { version } = serde_json::from_str(&text)?;
+    if version > SAVE_VERSION {
+        return Err(
+            format!("file version {version} is newer than the supported {SAVE_VERSION}").into(),
+        );
+    }
+
+    Ok(serde_json::from_str(&text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use bevy::ecs::system::RunSystemOnce;
+    use sun_orbit_simulation::{
+        math::{calendar::SECONDS_PER_DAY, physics::ForceModel},
+        scenario::Scenario,
+    };
+
+    use super::*;
+    use crate::{
+        collision::merge_collisions,
+        headless::{HeadlessPlugin, HeadlessRun},
+        planet::InitialScenario,
+        recorder::Recorder,
+    };
+
+    /// A heavy and a light body already touching, and a moon of the light one, as they start.
+    fn start() -> (World, [Entity; 3]) {
+        let mut world = World::new();
+        world.init_resource::<SaveRequests>();
+        world.init_resource::<Gui>();
+        world.init_resource::<SimulationTime>();
+        world.init_resource::<Checkpoints>();
+        world.init_resource::<PrecessionMeter>();
+        world.spawn((PointCamera::new(), MainCamera));
+
+        let heavy = Planet::bare(2e24, DVec2::ZERO, DVec2::ZERO);
+        let light = Planet::bare(1e24, DVec2::new(1e6, 0.0), DVec2::new(0.0, 1e3));
+        let moon = Planet::bare(1e20, DVec2::new(1e9, 0.0), DVec2::ZERO);
+        let heavy = world
+            .spawn((Name::new("Heavy"), heavy, Trail::default()))
+            .id();
+        let light = world
+            .spawn((Name::new("Light"), light, Trail::default()))
+            .id();
+        let moon = world
+            .spawn((
+                Name::new("Moon"),
+                moon,
+                Satellite {
+                    parent: light,
+                    innermost_distance: 1e9,
+                },
+                Trail::default(),
+            ))
+            .id();
+        (world, [heavy, light, moon])
+    }
+
+    fn save(world: &mut World, path: &Path) {
+        world.resource_mut::<SaveRequests>().save = Some(path.to_path_buf());
+        world.run_system_once(save_state).unwrap();
+    }
+
+    fn load(world: &mut World, path: &Path) {
+        world.resource_mut::<SaveRequests>().load = Some(path.to_path_buf());
+        world.run_system_once(load_state).unwrap();
+        let status = world.resource::<SaveRequests>().status.clone();
+        assert!(status.unwrap().starts_with("Loaded"));
+    }
+
+    fn total_mass(world: &mut World) -> f64 {
+        let mut planets = world.query::<&Planet>();
+        planets.iter(world).map(|planet| planet.mass).sum()
+    }
+
+    #[test]
+    fn loading_merges_and_unmerges_bodies() {
+        let directory = env::temp_dir();
+        let before = directory.join(format!("before-merge-{}.json", process::id()));
+        let after = directory.join(format!("after-merge-{}.json", process::id()));
+
+        let (mut world, [heavy, light, moon]) = start();
+        save(&mut world, &before);
+        world.run_system_once(merge_collisions).unwrap();
+        save(&mut world, &after);
+
+        // a fresh start loading the merged state doesn't get the light body's mass twice
+        let (mut fresh, [fresh_heavy, fresh_light, fresh_moon]) = start();
+        load(&mut fresh, &after);
+        assert!(fresh.get::<Absorbed>(fresh_light).is_some());
+        assert!(fresh.get::<Planet>(fresh_light).is_none());
+        let merged = fresh.get::<Planet>(fresh_heavy).unwrap();
+        assert_eq!(merged.mass, 3e24);
+        assert!(merged.physical_radius > 1e8);
+        assert_eq!(
+            fresh.get::<Satellite>(fresh_moon).unwrap().parent,
+            fresh_heavy
+        );
+        assert_eq!(total_mass(&mut fresh), 3e24 + 1e20);
+
+        // the merged simulation loading the state from before brings the light body back
+        load(&mut world, &before);
+        assert!(world.get::<Absorbed>(light).is_none());
+        assert_eq!(world.get::<Planet>(light).unwrap().mass, 1e24);
+        assert_eq!(world.get::<Planet>(heavy).unwrap().mass, 2e24);
+        assert_eq!(world.get::<Planet>(heavy).unwrap().physical_radius, 1e8);
+        assert_eq!(world.get::<Satellite>(moon).unwrap().parent, light);
+        assert_eq!(total_mass(&mut world), 3e24 + 1e20);
+
+        fs::remove_file(before).unwrap();
+        fs::remove_file(after).unwrap();
+    }
+
+    /// The solar system simulated without a window for `days` in whole day steps, from `load` on
+    /// if given.
+    fn headless(days: f64, load: Option<SaveFile>) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, HeadlessPlugin))
+            .insert_resource(InitialScenario(Scenario::solar_system()))
+            .insert_resource(Recorder::default())
+            .insert_resource(SimulationTime {
+                fixed_step: Some(SECONDS_PER_DAY),
+                ..Default::default()
+            })
+            .insert_resource(HeadlessRun {
+                end: days * SECONDS_PER_DAY,
+                forces: ForceModel::default(),
+                integrator: Integrator::default(),
+                output: None,
+                load,
+                finished: false,
+            });
+        while !app.world().resource::<HeadlessRun>().finished {
+            app.update();
+        }
+        app
+    }
+
+    #[test]
+    fn headless_runs_continue_from_saves() {
+        let path = env::temp_dir().join(format!("headless-save-{}.json", process::id()));
+
+        // saved halfway, without a window there's no camera or ui to save either, so they're
+        // the defaults
+        let mut halfway = headless(30.0, None);
+        let world = halfway.world_mut();
+        world.init_resource::<SaveRequests>();
+        world.init_resource::<Gui>();
+        world.spawn((PointCamera::new(), MainCamera));
+        save(world, &path);
+
+        let mut continued = headless(30.0, Some(read_save(&path).unwrap()));
+        let mut whole = headless(60.0, None);
+        fs::remove_file(path).unwrap();
+
+        let states = |app: &mut App| {
+            let world = app.world_mut();
+            let elapsed = world.resource::<SimulationTime>().elapsed;
+            let mut planets = world.query::<(&Name, &Planet)>();
+            let planets = planets
+                .iter(world)
+                .map(|(name, planet)| (name.to_string(), (planet.position, planet.velocity)))
+                .collect::<HashMap<_, _>>();
+            (elapsed, planets)
+        };
+        let (continued_elapsed, continued) = states(&mut continued);
+        let (elapsed, whole) = states(&mut whole);
+        assert_eq!(continued_elapsed, elapsed);
+
+        // the numbers in the file can be a bit off in their last digit, which the moons spread
+        // the most
+        assert_eq!(continued.len(), whole.len());
+        for (name, (position, velocity)) in whole {
+            let (continued_position, continued_velocity) = continued[&name];
+            let off = |a: DVec2, b: DVec2| (a - b).length() / b.length();
+            assert!(
+                off(continued_position, position) < 1e-9,
+                "{name} moved elsewhere"
+            );
+            assert!(
+                off(continued_velocity, velocity) < 1e-9,
+                "{name} moved elsewhere"
+            );
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use bevy::{math::DVec2, prelude::*};
+
+use sun_orbit_simulation::math::scaling::DistanceScaling;
+
+use crate::ui::egui::Gui;
+
+const TRANSITION_DURATION: f32 = 1.5; // seconds
+
+pub struct ScalingPlugin;
+
+impl Plugin for ScalingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DistanceScale>()
+            .add_systems(PreUpdate, animate_distance_scale);
+    }
+}
+
+/// Distance scaling used for rendering, after switching modes it blends from the previous one
+/// for a while instead of snapping.
+#[derive(Resource)]
+pub struct DistanceScale {
+    from: DistanceScaling,
+    to: DistanceScaling,
+    progress: f32, // 0.0 renders `from`, 1.0 renders `to`
+}
+
+impl Default for DistanceScale {
+    fn default() -> Self {
+        Self {
+            from: DistanceScaling::default(),
+            to: DistanceScaling::default(),
+            progress: 1.0,
+        }
+    }
+}
+
+impl DistanceScale {
+    pub fn scale_distance_to_bevy(&self, pos: DVec2) -> Vec2 {
+        let to = self.to.scale_distance_to_bevy(pos);
+        if self.progress >= 1.0 {
+            return to;
+        }
+
+        self.from
+            .scale_distance_to_bevy(pos)
+            .lerp(to, self.weight())
+    }
+
+    pub fn scale_satellite_distance_to_bevy(
+        &self,
+        pos: DVec2,
+        innermost_distance: f64,
+        parent_radius: f32,
+    ) -> Vec2 {
+        let to = self
+            .to
+            .scale_satellite_distance_to_bevy(pos, innermost_distance, parent_radius);
+        if self.progress >= 1.0 {
+            return to;
+        }
+
+        self.from
+            .scale_satellite_distance_to_bevy(pos, innermost_distance, parent_radius)
+            .lerp(to, self.weight())
+    }
+
+    pub fn radius(&self, render_radius: f32, physical_radius: f64, distance: f64) -> f32 {
+        let to = self.to.radius(render_radius, physical_radius, distance);
+        if self.progress >= 1.0 {
+            return to;
+        }
+
+        self.from
+            .radius(render_radius, physical_radius, distance)
+            .lerp(to, self.weight())
+    }
+
+    // eases in and out of the transition
+    fn weight(&self) -> f32 {
+        self.progress * self.progress * (3.0 - 2.0 * self.progress)
+    }
+}
+
+fn animate_distance_scale(time: Res<Time>, settings: Res<Gui>, mut scale: ResMut<DistanceScale>) {
+    let target = settings.distance_scaling;
+
+    if target != scale.to {
+        if target.mode == scale.to.mode && target.true_scale_radii == scale.to.true_scale_radii {
+            // dragging a slider shouldn't restart the transition every frame
+            scale.to = target;
+        } else {
+            // switching again mid-transition starts from whichever side is closer
+            if scale.progress >= 0.5 {
+                scale.from = scale.to;
+            }
+            scale.to = target;
+            scale.progress = 0.0;
+        }
+    }
+
+    if scale.progress < 1.0 {
+        scale.progress = (scale.progress + time.delta_secs() / TRANSITION_DURATION).min(1.0);
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    error::Error,
+    f64::consts::{PI, TAU},
+    fs,
+    path::Path,
+};
+
+use bevy_math::DVec2;
+use serde::Deserialize;
+use tracing::warn;
+
+use crate::{
+    constants::*,
+    math::{calendar::J2000, particles::ParticleState},
+};
+
+// the main belt, wide enough to show all the Kirkwood gaps from the 3:1 to the 2:1 resonance
+const BELT_INNER: f64 = 2.0 * AU;
+const BELT_OUTER: f64 = 3.5 * AU;
+const BELT_SPEED_SPREAD: f64 = 0.03; // relative to circular speed, eccentricities up to ~0.06
+
+const TROJAN_ANGLE_SPREAD: f64 = 10.0; // degrees around the Lagrange points
+const TROJAN_DISTANCE_SPREAD: f64 = 0.02; // relative to the planet's distance
+
+/// Initial state and physical properties of a single body, how it looks is up to the viewer.
+#[derive(Clone)]
+pub struct BodyConfig {
+    pub name: String,
+    pub parent: Option<String>, // name of the body this one orbits, `None` means the sun
+    pub mass: f64,
+    pub position: DVec2, // relative to the parent
+    pub velocity: DVec2, // relative to the parent
+    pub physical_radius: f64,
+    pub obliquity: f64,       // degrees
+    pub rotation_period: f64, // seconds
+}
+
+/// A body with its initial state resolved from relative to absolute.
+pub struct InitialBody<'a> {
+    pub config: &'a BodyConfig,
+    pub position: DVec2,
+    pub velocity: DVec2,
+    pub satellite: Option<(usize, f64)>, // index of the parent and the innermost moon distance
+}
+
+#[derive(Clone)]
+pub struct Scenario {
+    pub epoch: f64, // julian date of the initial conditions
+    pub bodies: Vec<BodyConfig>,
+    pub particles: Vec<ParticleState>, // massless, absolute
+}
+
+impl Scenario {
+    /// The eight planets starting at their aphelion, all lined up on the X axis, and their
+    /// largest moons.
+    pub fn solar_system() -> Self {
+        let planets = [
+            (
+                "Mercury",
+                MERCURY_MASS,
+                MERCURY_POS_X,
+                MERCURY_VEL_Y,
+                MERCURY_MEAN_RADIUS,
+                MERCURY_OBLIQUITY,
+                MERCURY_ROTATION_PERIOD,
+            ),
+            (
+                "Venus",
+                VENUS_MASS,
+                VENUS_POS_X,
+                VENUS_VEL_Y,
+                VENUS_MEAN_RADIUS,
+                VENUS_OBLIQUITY,
+                VENUS_ROTATION_PERIOD,
+            ),
+            (
+                "Earth",
+                EARTH_MASS,
+                EARTH_POS_X,
+                EARTH_VEL_Y,
+                EARTH_MEAN_RADIUS,
+                EARTH_OBLIQUITY,
+                EARTH_ROTATION_PERIOD,
+            ),
+            (
+                "Mars",
+                MARS_MASS,
+                MARS_POS_X,
+                MARS_VEL_Y,
+                MARS_MEAN_RADIUS,
+                MARS_OBLIQUITY,
+                MARS_ROTATION_PERIOD,
+            ),
+            (
+                "Jupiter",
+                JUPITER_MASS,
+                JUPITER_POS_X,
+                JUPITER_VEL_Y,
+                JUPITER_MEAN_RADIUS,
+                JUPITER_OBLIQUITY,
+                JUPITER_ROTATION_PERIOD,
+            ),
+            (
+                "Saturn",
+                SATURN_MASS,
+                SATURN_POS_X,
+                SATURN_VEL_Y,
+                SATURN_MEAN_RADIUS,
+                SATURN_OBLIQUITY,
+                SATURN_ROTATION_PERIOD,
+            ),
+            (
+                "Uranus",
+                URANUS_MASS,
+                URANUS_POS_X,
+                URANUS_VEL_Y,
+                URANUS_MEAN_RADIUS,
+                URANUS_OBLIQUITY,
+                URANUS_ROTATION_PERIOD,
+            ),
+            (
+                "Neptune",
+                NEPTUNE_MASS,
+                NEPTUNE_POS_X,
+                NEPTUNE_VEL_Y,
+                NEPTUNE_MEAN_RADIUS,
+                NEPTUNE_OBLIQUITY,
+                NEPTUNE_ROTATION_PERIOD,
+            ),
+        ];
+
+        let moons = [
+            (
+                "Moon",
+                "Earth",
+                MOON_MASS,
+                MOON_POS_X,
+                MOON_VEL_Y,
+                MOON_MEAN_RADIUS,
+                MOON_ROTATION_PERIOD,
+            ),
+            (
+                "Io",
+                "Jupiter",
+                IO_MASS,
+                IO_POS_X,
+                IO_VEL_Y,
+                IO_MEAN_RADIUS,
+                IO_ROTATION_PERIOD,
+            ),
+            (
+                "Europa",
+                "Jupiter",
+                EUROPA_MASS,
+                EUROPA_POS_X,
+                EUROPA_VEL_Y,
+                EUROPA_MEAN_RADIUS,
+                EUROPA_ROTATION_PERIOD,
+            ),
+            (
+                "Ganymede",
+                "Jupiter",
+                GANYMEDE_MASS,
+                GANYMEDE_POS_X,
+                GANYMEDE_VEL_Y,
+                GANYMEDE_MEAN_RADIUS,
+                GANYMEDE_ROTATION_PERIOD,
+            ),
+            (
+                "Callisto",
+                "Jupiter",
+                CALLISTO_MASS,
+                CALLISTO_POS_X,
+                CALLISTO_VEL_Y,
+                CALLISTO_MEAN_RADIUS,
+                CALLISTO_ROTATION_PERIOD,
+            ),
+            (
+                "Titan",
+                "Saturn",
+                TITAN_MASS,
+                TITAN_POS_X,
+                TITAN_VEL_Y,
+                TITAN_MEAN_RADIUS,
+                TITAN_ROTATION_PERIOD,
+            ),
+        ];
+
+        let planets = planets.into_iter().map(
+            |(name, mass, pos, vel, physical_radius, obliquity, rotation_period)| BodyConfig {
+                name: name.to_string(),
+                parent: None,
+                mass,
+                position: DVec2::new(pos, 0.0),
+                velocity: DVec2::new(0.0, vel),
+                physical_radius,
+                obliquity,
+                rotation_period,
+            },
+        );
+
+        let moons = moons.into_iter().map(
+            |(name, parent, mass, pos, vel, physical_radius, rotation_period)| BodyConfig {
+                name: name.to_string(),
+                parent: Some(parent.to_string()),
+                mass,
+                position: DVec2::new(pos, 0.0),
+                velocity: DVec2::new(0.0, vel),
+                physical_radius,
+                obliquity: 0.0,
+                rotation_period,
+            },
+        );
+
+        // parents have to come before their moons
+        Self {
+            epoch: J2000,
+            bodies: planets.chain(moons).collect(),
+            particles: vec![],
+        }
+    }
+
+    /// Reads a scenario from a JSON file, the sun isn't part of it. Only the physical side of the
+    /// bodies is read, the viewer picks the appearance fields out of the same file.
+    pub fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file: ScenarioFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        let bodies = file
+            .bodies
+            .into_iter()
+            .map(|body| BodyConfig {
+                name: body.name,
+                parent: body.parent,
+                mass: body.mass,
+                position: body.position.into(),
+                velocity: body.velocity.into(),
+                physical_radius: body.physical_radius,
+                obliquity: body.obliquity,
+                rotation_period: body.rotation_period,
+            })
+            .collect::<Vec<_>>();
+
+        // moons are resolved against bodies spawned before them
+        for (i, body) in bodies.iter().enumerate() {
+            if let Some(parent) = &body.parent
+                && !bodies[..i].iter().any(|other| other.name == *parent)
+            {
+                return Err(format!("{} has to come after its parent {parent}", body.name).into());
+            }
+        }
+
+        Ok(Self {
+            epoch: file.epoch,
+            bodies,
+            particles: file
+                .particles
+                .into_iter()
+                .map(|particle| ParticleState {
+                    position: particle.position.into(),
+                    velocity: particle.velocity.into(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Adds `count` test particles on nearly circular orbits around the sun, spread evenly over
+    /// the main asteroid belt. The same count always gives the same belt.
+    pub fn add_asteroid_belt(&mut self, count: usize) {
+        let mut random = SplitMix64(count as u64);
+        for _ in 0..count {
+            let distance = BELT_INNER + (BELT_OUTER - BELT_INNER) * random.next_f64();
+            let direction = DVec2::from_angle(TAU * random.next_f64());
+            let speed = (G * SUN_MASS / distance).sqrt()
+                * (1.0 + BELT_SPEED_SPREAD * (2.0 * random.next_f64() - 1.0));
+
+            self.particles.push(ParticleState {
+                position: direction * distance,
+                velocity: direction.perp() * speed,
+            });
+        }
+    }
+
+    /// Adds `count` test particles around the L4 and L5 points of a planet orbiting the sun,
+    /// half leading and half trailing it. Fails if there's no such planet.
+    pub fn add_trojans(&mut self, count: usize, planet: &str) -> Result<(), String> {
+        let Some(body) = self
+            .bodies
+            .iter()
+            .find(|body| body.name == planet && body.parent.is_none())
+        else {
+            return Err(format!("there's no planet called {planet}"));
+        };
+
+        let mut random = SplitMix64(count as u64);
+        for i in 0..count {
+            // the Lagrange points lead and trail the planet by 60°
+            let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+            let spread = TROJAN_ANGLE_SPREAD.to_radians() * (2.0 * random.next_f64() - 1.0);
+            let rotation = DVec2::from_angle(side * PI / 3.0 + spread);
+            let scale = 1.0 + TROJAN_DISTANCE_SPREAD * (2.0 * random.next_f64() - 1.0);
+
+            // a wider orbit is a slower one
+            self.particles.push(ParticleState {
+                position: rotation.rotate(body.position) * scale,
+                velocity: rotation.rotate(body.velocity) / scale.sqrt(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Bodies in the order they have to be spawned, moons whose parent is missing are skipped.
+    pub fn initial_bodies(&self) -> Vec<InitialBody<'_>> {
+        let mut bodies: Vec<InitialBody> = Vec::new();
+
+        for config in &self.bodies {
+            let parent = match &config.parent {
+                Some(name) => match bodies.iter().position(|body| body.config.name == *name) {
+                    Some(parent) => Some(parent),
+                    None => {
+                        warn!("Skipping {}, its parent {name} wasn't spawned", config.name);
+                        continue;
+                    }
+                },
+                None => None,
+            };
+
+            let (position, velocity) = match parent {
+                Some(parent) => (
+                    bodies[parent].position + config.position,
+                    bodies[parent].velocity + config.velocity,
+                ),
+                None => (config.position, config.velocity),
+            };
+
+            let satellite = parent.zip(
+                config
+                    .parent
+                    .as_deref()
+                    .and_then(|name| self.innermost_satellite_distance(name)),
+            );
+
+            bodies.push(InitialBody {
+                config,
+                position,
+                velocity,
+                satellite,
+            });
+        }
+
+        bodies
+    }
+
+    /// Distance of the closest moon orbiting `parent`, the scale of its whole moon system is
+    /// derived from it.
+    pub fn innermost_satellite_distance(&self, parent: &str) -> Option<f64> {
+        self.bodies
+            .iter()
+            .filter(|body| body.parent.as_deref() == Some(parent))
+            .map(|body| body.position.length())
+            .reduce(f64::min)
+    }
+}
+
+/// Scenario as stored in a file, positions and velocities are in meters and meters per second
+/// relative to the parent, or the sun for bodies without one.
+#[derive(Deserialize)]
+struct ScenarioFile {
+    #[serde(default = "default_epoch")]
+    epoch: f64, // julian date
+    bodies: Vec<BodyFile>,
+    #[serde(default)]
+    particles: Vec<ParticleFile>,
+}
+
+#[derive(Deserialize)]
+struct ParticleFile {
+    position: [f64; 2],
+    velocity: [f64; 2],
+}
+
+#[derive(Deserialize)]
+struct BodyFile {
+    name: String,
+    #[serde(default)]
+    parent: Option<String>,
+    mass: f64,
+    position: [f64; 2],
+    velocity: [f64; 2],
+    physical_radius: f64,
+    #[serde(default)]
+    obliquity: f64,
+    #[serde(default = "default_rotation_period")]
+    rotation_period: f64,
+}
+
+/// Tiny deterministic random number generator, so generated particles don't need a dependency
+/// and come out the same every time.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_f64(&mut self) -> f64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        // the top 53 bits fill the mantissa of a number in [0, 1)
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn default_epoch() -> f64 {
+    J2000
+}
+
+// bodies without a rotation period don't spin
+fn default_rotation_period() -> f64 {
+    f64::INFINITY
+}
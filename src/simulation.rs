@@ -0,0 +1,91 @@
+use bevy_math::DVec2;
+
+use crate::{
+    constants::SUN_MASS,
+    math::{
+        particles::ParticleState,
+        physics::{BodyState, ForceModel, Integrator, step_bodies, track_bodies},
+    },
+    scenario::Scenario,
+};
+
+/// A scenario advanced in exact fixed steps, without any of the viewer. Nothing depends on the
+/// clock, so the same scenario, step and settings always end up in bit-identical states.
+pub struct Simulation {
+    pub names: Vec<String>,
+    pub bodies: Vec<BodyState>, // the sun first, then the scenario's bodies in order
+    pub particles: Vec<ParticleState>,
+    pub forces: ForceModel,
+    pub integrator: Integrator,
+    step: f64, // simulated seconds
+    steps: u64,
+}
+
+impl Simulation {
+    /// Bodies start in the rest frame of their barycenter, like in the viewer.
+    pub fn new(scenario: &Scenario, step: f64) -> Self {
+        let mut names = vec!["Sun".to_string()];
+        let mut bodies = vec![BodyState {
+            mass: SUN_MASS,
+            position: DVec2::ZERO,
+            velocity: DVec2::ZERO,
+        }];
+
+        for body in scenario.initial_bodies() {
+            names.push(body.config.name.clone());
+            bodies.push(BodyState {
+                mass: body.config.mass,
+                position: body.position,
+                velocity: body.velocity,
+            });
+        }
+
+        let (momentum, mass) = bodies
+            .iter()
+            .fold((DVec2::ZERO, 0.0), |(momentum, mass), body| {
+                (momentum + body.mass * body.velocity, mass + body.mass)
+            });
+        let mut particles = scenario.particles.clone();
+        for body in &mut bodies {
+            body.velocity -= momentum / mass;
+        }
+        for particle in &mut particles {
+            particle.velocity -= momentum / mass;
+        }
+
+        Self {
+            names,
+            bodies,
+            particles,
+            forces: ForceModel::default(),
+            integrator: Integrator::default(),
+            step,
+            steps: 0,
+        }
+    }
+
+    /// Takes `steps` steps, split into substeps like any other time delta, so a run matches
+    /// the viewer's fixed-step mode. Splitting a run into any number of calls gives the same
+    /// result.
+    pub fn advance(&mut self, steps: u64) {
+        for _ in 0..steps {
+            if self.particles.is_empty() {
+                step_bodies(self.step, &mut self.bodies, self.forces, self.integrator);
+                continue;
+            }
+
+            let track = track_bodies(self.step, &mut self.bodies, self.forces, self.integrator);
+            track.advance_all(&mut self.particles, self.forces.softening);
+        }
+        self.steps += steps;
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    /// Counted in steps, so it doesn't pick up rounding errors along the way.
+    pub fn elapsed(&self) -> f64 {
+        self.steps as f64 * self.step
+    }
+}
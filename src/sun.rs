@@ -1,6 +1,15 @@
-use bevy::prelude::*;
+use bevy::{math::DVec2, prelude::*};
 
-use crate::constants::SUN_RADIUS;
+use sun_orbit_simulation::constants::{
+    SUN_MASS, SUN_MEAN_RADIUS, SUN_OBLIQUITY, SUN_ROTATION_PERIOD,
+};
+
+use crate::{
+    appearance::SUN_RADIUS,
+    particle::TestParticle,
+    planet::{AxialRotation, Planet},
+    trail::Trail,
+};
 
 pub struct SunPlugin;
 
@@ -11,11 +20,16 @@ impl Plugin for SunPlugin {
             brightness: 1.0,
             ..Default::default()
         })
-        .add_systems(Startup, setup);
+        .add_systems(Startup, spawn_sun)
+        .add_systems(PostStartup, balance_momentum);
     }
 }
 
-fn setup(
+/// Marks the sun, it's a body like any other for physics but it's also the source of light.
+#[derive(Component)]
+pub struct Sun;
+
+pub fn spawn_sun(
     asset_server: Res<AssetServer>,
     mut cmds: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -32,19 +46,60 @@ fn setup(
 
     let mesh = meshes.add(Sphere::new(SUN_RADIUS).mesh().ico(8).unwrap());
 
-    // create sun sphere
-    cmds.spawn((Mesh3d(mesh), MeshMaterial3d(material), Transform::default()));
-
-    // create light coming from it
+    // create sun sphere, with the light coming from it
     cmds.spawn((
-        PointLight {
-            intensity: 100_000_000.0,
-            range: 10_000.0,
-            radius: SUN_RADIUS,
-            shadows_enabled: false,
-            color: Color::linear_rgb(1.0, 0.98, 0.9),
-            ..Default::default()
-        },
-        Transform::from_translation(Vec3::ZERO),
-    ));
+        Name::new("Sun"),
+        sun_body(),
+        Sun,
+        Trail::default(),
+        Mesh3d(mesh),
+        MeshMaterial3d(material),
+        Transform::default(),
+        AxialRotation::new(SUN_OBLIQUITY, SUN_ROTATION_PERIOD),
+    ))
+    .with_child(PointLight {
+        intensity: 100_000_000.0,
+        range: 10_000.0,
+        radius: SUN_RADIUS,
+        shadows_enabled: false,
+        color: Color::linear_rgb(1.0, 0.98, 0.9),
+        ..Default::default()
+    });
+}
+
+/// The sun at rest at the origin, the scenario's positions are relative to it.
+pub fn sun_body() -> Planet {
+    Planet {
+        mass: SUN_MASS,
+        position: DVec2::ZERO,
+        previous_position: DVec2::ZERO,
+        velocity: DVec2::ZERO,
+        orbit_points: vec![],
+        render_radius: SUN_RADIUS,
+        physical_radius: SUN_MEAN_RADIUS,
+    }
+}
+
+/// Moves all bodies into the rest frame of their barycenter, so the system stays in place
+/// instead of drifting away. Velocities relative to each other, like heliocentric ones from the
+/// scenario, don't change. Test particles are moved along, they're relative to the sun too.
+pub fn balance_momentum(
+    mut bodies: Query<(Entity, &mut Planet)>,
+    mut particles: Query<&mut TestParticle>,
+) {
+    // summed up in spawn order, like in the library's simulation
+    let (momentum, mass) = bodies
+        .iter()
+        .sort_by_key::<Entity, _>(|entity| entity.index())
+        .fold((DVec2::ZERO, 0.0), |(momentum, mass), (_, body)| {
+            (momentum + body.mass * body.velocity, mass + body.mass)
+        });
+
+    let velocity = momentum / mass;
+    for (_, mut body) in &mut bodies {
+        body.velocity -= velocity;
+    }
+    for mut particle in &mut particles {
+        particle.velocity -= velocity;
+    }
 }
@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+use bevy::{math::DVec2, prelude::*};
+
+use crate::{
+    frame::{FrameBodies, ReferenceFrame},
+    planet::{Planet, Satellite, SimulationTime, update_planet_physics, update_planet_transforms},
+    scaling::DistanceScale,
+    ui::egui::Gui,
+};
+
+const TRAIL_POINTS: usize = 1024;
+const TRAIL_INTERVAL: f64 = 86_400.0; // simulated seconds between two recorded points
+
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, record_trails.after(update_planet_physics))
+            .add_systems(Update, draw_trails.after(update_planet_transforms));
+    }
+}
+
+/// Path a body took recently, as seen from the reference frame selected while it was recorded.
+#[derive(Component, Default)]
+pub struct Trail {
+    points: VecDeque<DVec2>, // in meters, relative to the parent for moons
+}
+
+impl Trail {
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+}
+
+fn record_trails(
+    settings: Res<Gui>,
+    sim_time: Res<SimulationTime>,
+    mut last_sample: Local<f64>,
+    mut last_frame: Local<Option<(ReferenceFrame, Option<Entity>)>>,
+    bodies: FrameBodies,
+    mut trails: Query<(&mut Trail, &Planet, Option<&Satellite>)>,
+) {
+    // points recorded in another frame would make no sense in this one
+    let frame = (settings.reference_frame, settings.frame_body);
+    if *last_frame != Some(frame) {
+        *last_frame = Some(frame);
+        for (mut trail, ..) in &mut trails {
+            trail.clear();
+        }
+    }
+
+    if (sim_time.elapsed - *last_sample).abs() < TRAIL_INTERVAL {
+        return;
+    }
+    *last_sample = sim_time.elapsed;
+
+    let transform = settings
+        .reference_frame
+        .transform(settings.frame_body, &bodies, 1.0);
+
+    for (mut trail, planet, satellite) in &mut trails {
+        let parent = satellite.and_then(|s| bodies.get(s.parent).ok());
+        let point = match parent {
+            Some((parent, ..)) => transform.apply_vector(planet.position - parent.position),
+            None => transform.apply(planet.position),
+        };
+
+        trail.points.push_back(point);
+        if trail.points.len() > TRAIL_POINTS {
+            trail.points.pop_front();
+        }
+    }
+}
+
+fn draw_trails(
+    settings: Res<Gui>,
+    scale: Res<DistanceScale>,
+    mut gizmos: Gizmos,
+    trails: Query<(&Trail, &Transform, Option<&Satellite>), With<Planet>>,
+    parents: Query<(&Planet, &Transform)>,
+) {
+    if !settings.show_trails {
+        return;
+    }
+
+    for (trail, transform, satellite) in trails {
+        let parent = satellite.and_then(|s| Some((s, parents.get(s.parent).ok()?)));
+        let points = trail.points.iter().map(|p| match parent {
+            Some((satellite, (parent, parent_transform))) => {
+                let p = scale.scale_satellite_distance_to_bevy(
+                    *p,
+                    satellite.innermost_distance,
+                    parent.render_radius * parent_transform.scale.x,
+                );
+                parent_transform.translation + Vec3::new(p.x, 0.0, p.y)
+            }
+            None => {
+                let p = scale.scale_distance_to_bevy(*p);
+                Vec3::new(p.x, 0.0, p.y)
+            }
+        });
+
+        // fades out towards the oldest point and ends right at the body
+        let count = trail.points.len() as f32 + 1.0;
+        let gradient = points
+            .chain([transform.translation])
+            .enumerate()
+            .map(|(i, point)| {
+                (
+                    point,
+                    Color::linear_rgba(0.3, 0.3, 0.4, 0.8 * (i as f32 + 1.0) / count),
+                )
+            });
+
+        gizmos.linestrip_gradient(gradient);
+    }
+}
@@ -1,17 +1,84 @@
-use bevy::ecs::resource::Resource;
+use bevy::ecs::{
+    entity::Entity,
+    resource::Resource,
+    system::{ResMut, SystemParam},
+};
 use bevy_egui::{EguiContexts, egui};
+use serde::{Deserialize, Serialize};
+
+use sun_orbit_simulation::math::{
+    calendar::Date,
+    physics::{DEFAULT_OPENING_ANGLE, GravitySolver, Integrator},
+    scaling::{DistanceScaling, ScalingMode},
+};
+
+use crate::{
+    checkpoint::Checkpoints, frame::ReferenceFrame, planet::SimulationTime,
+    precession::PrecessionMeter, recorder::Recorder, save::SaveRequests,
+};
+
+pub const MIN_TIME_RATE: f64 = 1.0; // real time
+pub const MAX_TIME_RATE: f64 = 10.0 * YEAR;
+
+const DAY: f64 = 86_400.0;
+const YEAR: f64 = 365.25 * DAY;
 
 #[derive(Resource)]
 pub struct Gui {
     pub open: bool,
     pub show_performance: bool,
-    pub time_scale: TimeScale,
+    pub time: TimeSettings,
+    pub jump_date: String, // typed in by the user
+    pub save_path: String,
+    pub record_path: String,
+    pub relativity: bool,
+    pub softening: f64, // meters
+    pub close_encounters: bool,
+    pub solver: GravitySolver,
+    pub integrator: Integrator,
+    pub precession_body: Option<Entity>, // body whose periapsis precession is measured
     pub show_orbits: bool,
+    pub show_faint_rings: bool,
+    pub show_trails: bool,
+    pub distance_scaling: DistanceScaling,
+    pub reference_frame: ReferenceFrame,
+    pub frame_body: Option<Entity>, // for frames centered on a body
+    pub main_focus: Option<Entity>,
+    pub overview: OverviewSettings,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct TimeSettings {
+    pub rate: f64, // simulated seconds per real second
+    pub reverse: bool,
+    pub paused: bool,
+}
+
+pub struct OverviewSettings {
+    pub enabled: bool,
+    pub focus: Option<Entity>,
+    pub corner: ViewportCorner,
+    pub size: f32, // fraction of the window size
+}
+
+/// Simulation state the ui controls besides its own settings.
+#[derive(SystemParam)]
+pub struct Controls<'w> {
+    pub sim_time: ResMut<'w, SimulationTime>,
+    pub checkpoints: ResMut<'w, Checkpoints>,
+    pub precession: ResMut<'w, PrecessionMeter>,
+    pub saves: ResMut<'w, SaveRequests>,
+    pub recorder: ResMut<'w, Recorder>,
 }
 
 // unfortunately i had to embed it to make it work
 impl Gui {
-    pub fn draw(&mut self, contexts: &mut EguiContexts) -> bevy::prelude::Result {
+    pub fn draw(
+        &mut self,
+        contexts: &mut EguiContexts,
+        bodies: &[(Entity, String)],
+        controls: &mut Controls,
+    ) -> bevy::prelude::Result {
         egui::Window::new("Debug")
             .collapsible(false)
             .resizable(false)
@@ -20,43 +87,434 @@ impl Gui {
                 ui.set_width(300.0);
                 ui.checkbox(&mut self.show_performance, "Show performance stats");
                 ui.checkbox(&mut self.show_orbits, "Show orbits");
+                ui.checkbox(&mut self.show_faint_rings, "Show faint rings");
+                ui.checkbox(&mut self.show_trails, "Show trails");
+                save_ui(ui, &mut self.save_path, &mut controls.saves);
+                record_ui(ui, &mut self.record_path, &mut controls.recorder);
+
+                calendar_ui(ui, &mut self.jump_date, &mut controls.sim_time);
+                timeline_ui(ui, &controls.sim_time, &mut controls.checkpoints);
 
-                egui::ComboBox::from_label("Time scale")
-                    .selected_text(format!("1s = {:?}", self.time_scale))
+                time_ui(ui, &mut self.time, &mut controls.sim_time);
+
+                ui.separator();
+                egui::ComboBox::from_label("Integrator")
+                    .selected_text(self.integrator.label())
+                    .show_ui(ui, |ui| {
+                        for integrator in Integrator::ALL {
+                            ui.selectable_value(
+                                &mut self.integrator,
+                                integrator,
+                                integrator.label(),
+                            );
+                        }
+                    });
+                ui.checkbox(&mut self.relativity, "General relativity (1PN)");
+                solver_ui(ui, &mut self.solver);
+                softening_ui(ui, &mut self.softening);
+                ui.checkbox(
+                    &mut self.close_encounters,
+                    "Shorter steps in close encounters",
+                );
+                precession_ui(
+                    ui,
+                    &mut self.precession_body,
+                    bodies,
+                    &mut controls.precession,
+                );
+
+                ui.separator();
+                distance_scaling_ui(ui, &mut self.distance_scaling);
+
+                egui::ComboBox::from_label("Reference frame")
+                    .selected_text(self.reference_frame.label())
                     .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.time_scale, TimeScale::Day, "Day");
-                        ui.selectable_value(&mut self.time_scale, TimeScale::Month, "Month");
-                        ui.selectable_value(&mut self.time_scale, TimeScale::Year, "Year");
+                        for frame in ReferenceFrame::ALL {
+                            ui.selectable_value(&mut self.reference_frame, frame, frame.label());
+                        }
                     });
+                ui.add_enabled_ui(self.reference_frame.needs_body(), |ui| {
+                    body_combo_box(ui, "Frame body", "None", &mut self.frame_body, bodies);
+                });
+
+                ui.separator();
+                body_combo_box(ui, "Camera focus", "Origin", &mut self.main_focus, bodies);
+
+                ui.checkbox(&mut self.overview.enabled, "Show overview");
+                ui.add_enabled_ui(self.overview.enabled, |ui| {
+                    body_combo_box(
+                        ui,
+                        "Overview focus",
+                        "Origin",
+                        &mut self.overview.focus,
+                        bodies,
+                    );
+
+                    egui::ComboBox::from_label("Overview corner")
+                        .selected_text(format!("{:?}", self.overview.corner))
+                        .show_ui(ui, |ui| {
+                            for corner in [
+                                ViewportCorner::TopLeft,
+                                ViewportCorner::TopRight,
+                                ViewportCorner::BottomLeft,
+                                ViewportCorner::BottomRight,
+                            ] {
+                                ui.selectable_value(
+                                    &mut self.overview.corner,
+                                    corner,
+                                    format!("{corner:?}"),
+                                );
+                            }
+                        });
+
+                    ui.add(
+                        egui::Slider::new(&mut self.overview.size, 0.1..=0.5).text("Overview size"),
+                    );
+                });
             });
         Ok(())
     }
 }
 
+fn distance_scaling_ui(ui: &mut egui::Ui, scaling: &mut DistanceScaling) {
+    let scientific = |value: f64, _| format!("{value:.2e}");
+
+    egui::ComboBox::from_label("Distance scale")
+        .selected_text(format!("{:?}", scaling.mode))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut scaling.mode, ScalingMode::Linear, "Linear");
+            ui.selectable_value(&mut scaling.mode, ScalingMode::Logarithmic, "Logarithmic");
+            ui.selectable_value(&mut scaling.mode, ScalingMode::Compressed, "Compressed");
+        });
+
+    match scaling.mode {
+        ScalingMode::Linear => {
+            ui.add(
+                egui::Slider::new(&mut scaling.meters_per_unit, 1e8..=1e11)
+                    .logarithmic(true)
+                    .custom_formatter(scientific)
+                    .text("Meters per unit"),
+            );
+        }
+        ScalingMode::Logarithmic => {
+            ui.add(
+                egui::Slider::new(&mut scaling.log_reference, 1e9..=1e12)
+                    .logarithmic(true)
+                    .custom_formatter(scientific)
+                    .text("Reference distance (m)"),
+            );
+            ui.add(
+                egui::Slider::new(&mut scaling.log_units, 10.0..=200.0).text("Units per e-fold"),
+            );
+        }
+        ScalingMode::Compressed => {
+            ui.add(
+                egui::Slider::new(&mut scaling.a, 1e-26..=1e-23)
+                    .logarithmic(true)
+                    .custom_formatter(scientific)
+                    .text("Steepness"),
+            );
+            ui.add(
+                egui::Slider::new(&mut scaling.cut_out, 1e10..=1e12)
+                    .logarithmic(true)
+                    .custom_formatter(scientific)
+                    .text("Cut out (m)"),
+            );
+            ui.add(egui::Slider::new(&mut scaling.exponent, 0.1..=1.0).text("Exponent"));
+            ui.add(
+                egui::Slider::new(&mut scaling.distance_scale, 1e9..=1e11)
+                    .logarithmic(true)
+                    .custom_formatter(scientific)
+                    .text("Meters per unit"),
+            );
+        }
+    }
+
+    ui.checkbox(&mut scaling.true_scale_radii, "True scale radii");
+}
+
+fn save_ui(ui: &mut egui::Ui, path: &mut String, saves: &mut SaveRequests) {
+    ui.horizontal(|ui| {
+        ui.add(egui::TextEdit::singleline(path).desired_width(150.0));
+        if ui.button("Save").clicked() {
+            saves.save = Some(path.as_str().into());
+        }
+        if ui.button("Load").clicked() {
+            saves.load = Some(path.as_str().into());
+        }
+    });
+
+    if let Some(status) = &saves.status {
+        ui.label(status);
+    }
+}
+
+fn record_ui(ui: &mut egui::Ui, path: &mut String, recorder: &mut Recorder) {
+    ui.horizontal(|ui| {
+        let recording = recorder.is_recording();
+        ui.add_enabled(
+            !recording,
+            egui::TextEdit::singleline(path).desired_width(150.0),
+        );
+        if recording {
+            if ui.button("Stop").clicked() {
+                recorder.stop();
+            }
+        } else if ui.button("Record").clicked()
+            && let Err(err) = recorder.start(path.as_str().into())
+        {
+            recorder.status = Some(format!("Couldn't record to {path}: {err}"));
+        }
+    });
+
+    let mut days = recorder.interval / DAY;
+    let slider = egui::Slider::new(&mut days, 0.01..=365.0)
+        .logarithmic(true)
+        .text("Sample interval (days)");
+    if ui.add(slider).changed() {
+        recorder.interval = days * DAY;
+    }
+
+    if let Some(status) = &recorder.status {
+        ui.label(status);
+    }
+}
+
+fn solver_ui(ui: &mut egui::Ui, solver: &mut GravitySolver) {
+    let mut barnes_hut = matches!(solver, GravitySolver::BarnesHut { .. });
+    if ui.checkbox(&mut barnes_hut, "Barnes-Hut tree").changed() {
+        *solver = if barnes_hut {
+            GravitySolver::BarnesHut {
+                opening_angle: DEFAULT_OPENING_ANGLE,
+            }
+        } else {
+            GravitySolver::Direct
+        };
+    }
+
+    if let GravitySolver::BarnesHut { opening_angle } = solver {
+        ui.add(egui::Slider::new(opening_angle, 0.1..=1.5).text("Opening angle (rad)"));
+    }
+}
+
+// shown in kilometers, 0 turns it off
+fn softening_ui(ui: &mut egui::Ui, softening: &mut f64) {
+    let mut km = *softening / 1000.0;
+    let slider = egui::Slider::new(&mut km, 0.0..=1e7)
+        .logarithmic(true)
+        .text("Softening (km)");
+    if ui.add(slider).changed() {
+        *softening = km * 1000.0;
+    }
+}
+
+fn time_ui(ui: &mut egui::Ui, time: &mut TimeSettings, sim_time: &mut SimulationTime) {
+    match sim_time.fixed_step {
+        Some(step) => {
+            ui.label(format!("Deterministic: {step} s per tick"));
+        }
+        None => {
+            ui.add(
+                egui::Slider::new(&mut time.rate, MIN_TIME_RATE..=MAX_TIME_RATE)
+                    .logarithmic(true)
+                    .custom_formatter(|rate, _| format_rate(rate))
+                    .text("Time rate"),
+            );
+        }
+    }
+
+    ui.horizontal(|ui| {
+        let pause = if time.paused { "Resume" } else { "Pause" };
+        if ui.button(pause).clicked() {
+            time.paused = !time.paused;
+        }
+        ui.add_enabled_ui(time.paused, |ui| {
+            if ui.button("Step").clicked() {
+                sim_time.step = true;
+            }
+        });
+        ui.checkbox(&mut time.reverse, "Reverse");
+    });
+    ui.label("Space: pause, .: step, R: reverse, [ ]: rate");
+}
+
+fn calendar_ui(ui: &mut egui::Ui, jump_date: &mut String, sim_time: &mut SimulationTime) {
+    let julian_date = sim_time.julian_date();
+    ui.label(format!("Date: {}", Date::from_julian_date(julian_date)));
+    ui.label(format!("JD: {julian_date:.4}"));
+
+    ui.horizontal(|ui| {
+        ui.add(
+            egui::TextEdit::singleline(jump_date)
+                .hint_text("YYYY-MM-DD [HH:MM] or JD")
+                .desired_width(200.0),
+        );
+
+        // plain numbers are julian dates
+        let target = jump_date
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .or_else(|| Date::parse(jump_date).map(|date| date.julian_date()));
+        ui.add_enabled_ui(target.is_some(), |ui| {
+            if ui.button("Jump").clicked()
+                && let Some(target) = target
+            {
+                sim_time.jump_to(target);
+            }
+        });
+    });
+
+    if sim_time.target.is_some() {
+        ui.label("Jumping...");
+    }
+}
+
+fn timeline_ui(ui: &mut egui::Ui, sim_time: &SimulationTime, checkpoints: &mut Checkpoints) {
+    let Some((first, last)) = checkpoints.range() else {
+        return;
+    };
+
+    let mut time = checkpoints
+        .restore
+        .or(sim_time.target)
+        .unwrap_or(sim_time.elapsed);
+    let response = ui.add(
+        // the date is already shown by the calendar
+        egui::Slider::new(
+            &mut time,
+            first.min(sim_time.elapsed)..=last.max(sim_time.elapsed),
+        )
+        .show_value(false)
+        .text("Timeline"),
+    );
+    if response.changed() {
+        checkpoints.restore = Some(time);
+    }
+
+    ui.label(format!(
+        "{} checkpoints, every {:.0} days",
+        checkpoints.count(),
+        checkpoints.interval() / DAY
+    ));
+}
+
+fn precession_ui(
+    ui: &mut egui::Ui,
+    body: &mut Option<Entity>,
+    bodies: &[(Entity, String)],
+    meter: &mut PrecessionMeter,
+) {
+    body_combo_box(ui, "Measure precession", "None", body, bodies);
+    if body.is_none() {
+        return;
+    }
+
+    match meter.rate() {
+        Some(rate) => ui.label(format!(
+            "Precession: {rate:.1}\"/century over {:.1} years",
+            meter.years()
+        )),
+        None => ui.label("Precession: measuring..."),
+    };
+    if let Some(rate) = meter.relativistic_rate() {
+        ui.label(format!("Minus Newtonian baseline: {rate:.1}\"/century"));
+    }
+    ui.label(format!(
+        "GR prediction: {:.1}\"/century",
+        meter.expected_rate()
+    ));
+    if ui.button("Reset measurement").clicked() {
+        meter.reset();
+    }
+}
+
+fn body_combo_box(
+    ui: &mut egui::Ui,
+    label: &str,
+    none_label: &str,
+    body: &mut Option<Entity>,
+    bodies: &[(Entity, String)],
+) {
+    let selected = body
+        .and_then(|entity| bodies.iter().find(|(e, _)| *e == entity))
+        .map_or(none_label, |(_, name)| name.as_str());
+
+    egui::ComboBox::from_label(label)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(body, None, none_label);
+            for (entity, name) in bodies {
+                ui.selectable_value(body, Some(*entity), name);
+            }
+        });
+}
+
 impl Default for Gui {
     fn default() -> Self {
         Self {
             open: true,
             show_performance: false,
-            time_scale: TimeScale::Day,
+            time: TimeSettings {
+                rate: DAY,
+                reverse: false,
+                paused: false,
+            },
+            jump_date: String::new(),
+            save_path: "simulation.json".to_string(),
+            record_path: "trajectory.csv".to_string(),
+            relativity: false,
+            softening: 0.0,
+            close_encounters: false,
+            solver: GravitySolver::default(),
+            integrator: Integrator::default(),
+            precession_body: None,
             show_orbits: true,
+            show_faint_rings: false,
+            show_trails: false,
+            distance_scaling: DistanceScaling::default(),
+            reference_frame: ReferenceFrame::Heliocentric,
+            frame_body: None,
+            main_focus: None,
+            overview: OverviewSettings {
+                enabled: false,
+                focus: None,
+                corner: ViewportCorner::BottomRight,
+                size: 0.3,
+            },
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
-pub enum TimeScale {
-    Day,
-    Month,
-    Year,
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ViewportCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
 }
 
-impl TimeScale {
-    pub fn to_seconds(&self) -> f64 {
-        match self {
-            Self::Day => 86_400.0,
-            Self::Month => 2.6298e6,
-            Self::Year => 3.15576e7,
-        }
+impl TimeSettings {
+    /// Simulated seconds per real second, negative when running backwards.
+    pub fn signed_rate(&self) -> f64 {
+        if self.reverse { -self.rate } else { self.rate }
+    }
+
+    pub fn faster(&mut self) {
+        self.rate = (self.rate * 2.0).min(MAX_TIME_RATE);
     }
+
+    pub fn slower(&mut self) {
+        self.rate = (self.rate / 2.0).max(MIN_TIME_RATE);
+    }
+}
+
+// e.g. "3.2 days/s"
+fn format_rate(rate: f64) -> String {
+    let (value, unit) = [(YEAR, "years"), (DAY, "days"), (3600.0, "h"), (60.0, "min")]
+        .into_iter()
+        .find(|(unit, _)| rate >= *unit)
+        .map_or((rate, "s"), |(unit, name)| (rate / unit, name));
+
+    format!("{value:.1} {unit}/s")
 }
@@ -1,3 +1,4 @@
 pub mod egui;
 mod performance;
 pub mod plugin;
+mod shortcuts;
@@ -7,7 +7,7 @@ use bevy::{
 };
 
 use crate::{
-    camera::{DEFAULT_DISTANCE, PointCamera},
+    camera::{DEFAULT_DISTANCE, MainCamera, PointCamera},
     ui::egui::Gui,
 };
 
@@ -72,7 +72,7 @@ pub fn update_camera_stats(
         Single<&mut TextSpan, With<YawText>>,
         Single<&mut TextSpan, With<ZoomText>>,
     )>,
-    camera: Single<&PointCamera>,
+    camera: Single<&PointCamera, With<MainCamera>>,
 ) {
     for mut node in all_text {
         node.display = if settings.show_performance {
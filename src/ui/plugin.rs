@@ -2,23 +2,35 @@ use bevy::{
     app::{App, Plugin, Startup, Update},
     diagnostic::FrameTimeDiagnosticsPlugin,
     ecs::{
-        schedule::{IntoScheduleConfigs, common_conditions::run_once},
-        system::{ResMut, Single},
+        entity::Entity,
+        query::With,
+        schedule::{
+            IntoScheduleConfigs,
+            common_conditions::{not, run_once},
+        },
+        system::{Query, ResMut, Single},
     },
-    prelude::Result,
+    prelude::{Name, Result},
 };
 use bevy_egui::{
     EguiContextSettings, EguiContexts, EguiPlugin, EguiPrimaryContextPass,
     egui::{FontId, TextStyle},
+    input::egui_wants_any_keyboard_input,
 };
 
-use crate::ui::{egui::Gui, performance};
+use crate::{
+    planet::Planet,
+    ui::{
+        egui::{Controls, Gui},
+        performance, shortcuts,
+    },
+};
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(Gui::default())
+        app.init_resource::<Gui>()
             .add_plugins((EguiPlugin::default(), FrameTimeDiagnosticsPlugin::default()))
             .add_systems(
                 EguiPrimaryContextPass,
@@ -30,6 +42,7 @@ impl Plugin for UiPlugin {
                 (
                     performance::update_fps_text,
                     performance::update_camera_stats,
+                    shortcuts::time_shortcuts.run_if(not(egui_wants_any_keyboard_input)),
                 ),
             );
     }
@@ -56,6 +69,16 @@ fn setup_fonts(mut contexts: EguiContexts) -> Result {
     Ok(())
 }
 
-fn draw_gui(mut contexts: EguiContexts, mut gui: ResMut<Gui>) -> Result {
-    gui.draw(&mut contexts)
+fn draw_gui(
+    mut contexts: EguiContexts,
+    mut gui: ResMut<Gui>,
+    mut controls: Controls,
+    planets: Query<(Entity, &Name), With<Planet>>,
+) -> Result {
+    let bodies = planets
+        .into_iter()
+        .map(|(entity, name)| (entity, name.to_string()))
+        .collect::<Vec<_>>();
+
+    gui.draw(&mut contexts, &bodies, &mut controls)
 }
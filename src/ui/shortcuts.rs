@@ -0,0 +1,30 @@
+use bevy::prelude::*;
+
+use crate::{planet::SimulationTime, ui::egui::Gui};
+
+/// Keyboard shortcuts for controlling the flow of simulated time.
+pub fn time_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<Gui>,
+    mut sim_time: ResMut<SimulationTime>,
+) {
+    let time = &mut settings.time;
+
+    if keys.just_pressed(KeyCode::Space) {
+        time.paused = !time.paused;
+    }
+    if keys.just_pressed(KeyCode::Period) {
+        // stepping only makes sense while paused
+        time.paused = true;
+        sim_time.step = true;
+    }
+    if keys.just_pressed(KeyCode::KeyR) {
+        time.reverse = !time.reverse;
+    }
+    if keys.just_pressed(KeyCode::BracketRight) {
+        time.faster();
+    }
+    if keys.just_pressed(KeyCode::BracketLeft) {
+        time.slower();
+    }
+}
@@ -0,0 +1,137 @@
+use std::f64::consts::PI;
+
+use bevy_math::DVec2;
+use sun_orbit_simulation::{
+    constants::{AU, EARTH_MASS, G, SUN_MASS},
+    math::{
+        barnes_hut::QuadTree,
+        physics::{BodyState, ForceModel, GravitySolver, compute_accelerations},
+    },
+};
+
+/// The sun and a spiral of bodies of different masses around it.
+fn spiral(count: usize) -> Vec<BodyState> {
+    let mut bodies = vec![BodyState {
+        mass: SUN_MASS,
+        position: DVec2::ZERO,
+        velocity: DVec2::ZERO,
+    }];
+    for i in 0..count {
+        let angle = i as f64 * PI * (3.0 - 5f64.sqrt());
+        bodies.push(BodyState {
+            mass: EARTH_MASS * (1 + i % 7) as f64,
+            position: DVec2::from_angle(angle) * AU * (0.5 + i as f64 / 10.0),
+            velocity: DVec2::ZERO,
+        });
+    }
+    bodies
+}
+
+fn tree_accelerations(bodies: &[BodyState], softening: f64) -> Vec<DVec2> {
+    let tree = QuadTree::new(bodies);
+    (0..bodies.len())
+        .map(|i| tree.acceleration_at(bodies, bodies[i].position, Some(i), 0.0, softening))
+        .collect()
+}
+
+#[test]
+fn zero_opening_angle_matches_direct_summation() {
+    let bodies = spiral(40);
+    let direct = compute_accelerations(&bodies, ForceModel::default());
+
+    for (tree, direct) in tree_accelerations(&bodies, 0.0).into_iter().zip(direct) {
+        let error = (tree - direct).length();
+        assert!(error <= 1e-12 * direct.length(), "off by {error}");
+    }
+}
+
+#[test]
+fn crowded_leaves_stay_finite() {
+    // a pair on the same spot and a pair closer than the deepest cells can split, far from the
+    // rest, so the tree gives up on splitting them
+    let mut bodies = spiral(10);
+    for offset in [0.0, 0.0, 1e-9, 2e-9] {
+        bodies.push(BodyState {
+            mass: EARTH_MASS,
+            position: DVec2::new(offset, AU),
+            velocity: DVec2::ZERO,
+        });
+    }
+
+    for softening in [0.0, 1e6] {
+        for acceleration in tree_accelerations(&bodies, softening) {
+            assert!(acceleration.is_finite());
+        }
+    }
+
+    // the sun doesn't care how they're bunched up
+    let sun = tree_accelerations(&bodies, 0.0)[0];
+    let direct = compute_accelerations(&bodies, ForceModel::default())[0];
+    assert!((sun - direct).length() <= 1e-12 * direct.length());
+}
+
+#[test]
+fn strongest_pull_matches_a_search_of_every_body() {
+    let bodies = spiral(60);
+    let tree = QuadTree::new(&bodies);
+    let pull = |at: DVec2, body: &BodyState| G * body.mass / at.distance_squared(body.position);
+
+    for (i, body) in bodies.iter().enumerate() {
+        let strongest = (0..bodies.len()).filter(|&j| j != i).max_by(|&a, &b| {
+            pull(body.position, &bodies[a]).total_cmp(&pull(body.position, &bodies[b]))
+        });
+        assert_eq!(
+            tree.strongest_at(&bodies, body.position, Some(i), 0.0),
+            strongest
+        );
+    }
+}
+
+#[test]
+fn bodies_within_a_box_match_a_search_of_every_body() {
+    let bodies = spiral(60);
+    let tree = QuadTree::new(&bodies);
+
+    for (min, max) in [
+        (DVec2::splat(-AU), DVec2::splat(AU)),
+        (DVec2::new(0.0, -3.0 * AU), DVec2::new(4.0 * AU, 0.5 * AU)),
+        (DVec2::splat(10.0 * AU), DVec2::splat(11.0 * AU)),
+        (bodies[7].position, bodies[7].position),
+    ] {
+        let mut found = tree.within(&bodies, min, max);
+        found.sort_unstable();
+        let inside = (0..bodies.len())
+            .filter(|&i| bodies[i].position.cmpge(min).all() && bodies[i].position.cmple(max).all())
+            .collect::<Vec<_>>();
+        assert_eq!(found, inside);
+    }
+}
+
+#[test]
+fn relativity_only_between_the_strongest_pairs_matches_every_pair() {
+    let mut bodies = spiral(40);
+    for body in &mut bodies[1..] {
+        let speed = (G * SUN_MASS / body.position.length()).sqrt();
+        body.velocity = body.position.perp().normalize() * speed;
+    }
+    let forces = |solver| ForceModel {
+        relativity: true,
+        solver,
+        ..Default::default()
+    };
+
+    let direct = compute_accelerations(&bodies, forces(GravitySolver::Direct));
+    let tree = compute_accelerations(
+        &bodies,
+        forces(GravitySolver::BarnesHut { opening_angle: 0.0 }),
+    );
+    let newtonian = compute_accelerations(&bodies, ForceModel::default());
+    for ((tree, direct), newtonian) in tree.into_iter().zip(direct).zip(newtonian) {
+        let correction = (direct - newtonian).length();
+        let error = (tree - direct).length();
+        assert!(
+            error <= 1e-3 * correction,
+            "off by {error} of a correction of {correction}"
+        );
+    }
+}
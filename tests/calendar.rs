@@ -0,0 +1,28 @@
+use sun_orbit_simulation::math::calendar::Date;
+
+#[test]
+fn days_past_the_end_of_the_month_are_rejected() {
+    for text in [
+        "2023-02-29",
+        "2023-02-31",
+        "2023-04-31",
+        "1900-02-29",
+        "1582-10-10",
+    ] {
+        assert_eq!(Date::parse(text), None, "{text} was accepted");
+    }
+}
+
+#[test]
+fn leap_days_are_accepted() {
+    // 1500 and 1000 are Julian leap years, 2000 a Gregorian one, -4 is 5 BC
+    for text in [
+        "2024-02-29",
+        "2000-02-29",
+        "1500-02-29",
+        "1000-02-29",
+        "-4-02-29",
+    ] {
+        assert!(Date::parse(text).is_some(), "{text} was rejected");
+    }
+}
@@ -0,0 +1,126 @@
+use bevy_math::DVec2;
+use sun_orbit_simulation::{
+    constants::{IO_MASS, IO_MEAN_RADIUS, IO_POS_X, IO_VEL_Y, JUPITER_MASS, JUPITER_MEAN_RADIUS},
+    math::{
+        collision::{merge, possible_contacts, swept_contact, touched},
+        physics::{BodyState, ForceModel, Integrator, orbital_period, step_bodies},
+    },
+};
+
+const REACH: f64 = JUPITER_MEAN_RADIUS + IO_MEAN_RADIUS;
+
+fn jupiter_and_io() -> Vec<BodyState> {
+    vec![
+        BodyState {
+            mass: JUPITER_MASS,
+            position: DVec2::ZERO,
+            velocity: DVec2::ZERO,
+        },
+        BodyState {
+            mass: IO_MASS,
+            position: DVec2::new(IO_POS_X, 0.0),
+            velocity: DVec2::new(0.0, IO_VEL_Y),
+        },
+    ]
+}
+
+/// Whether Jupiter and Io touched in a single tick of `dt`, like the viewer checks them.
+fn touched_after(dt: f64) -> bool {
+    let mut bodies = jupiter_and_io();
+    let previous = bodies.iter().map(|b| b.position).collect::<Vec<_>>();
+    step_bodies(dt, &mut bodies, ForceModel::default(), Integrator::Yoshida);
+
+    touched(&bodies[0], previous[0], &bodies[1], previous[1], REACH, dt)
+}
+
+#[test]
+fn moons_arent_swept_into_their_planet_by_long_ticks() {
+    let period = orbital_period(
+        DVec2::new(IO_POS_X, 0.0),
+        DVec2::new(0.0, IO_VEL_Y),
+        JUPITER_MASS + IO_MASS,
+    );
+
+    // half an orbit puts Io on the far side, the straight line between runs through Jupiter
+    for fraction in [0.01, 0.1, 0.3, 0.5, 0.7, 2.5] {
+        assert!(
+            !touched_after(fraction * period),
+            "Io hit Jupiter after {fraction} of an orbit"
+        );
+    }
+}
+
+#[test]
+fn fast_bodies_dont_skip_through_each_other() {
+    // an unbound body flying straight through Jupiter within a single tick
+    let jupiter = jupiter_and_io()[0];
+    let previous = DVec2::new(-1e9, 0.0);
+    let impactor = BodyState {
+        mass: IO_MASS,
+        position: DVec2::new(1e9, 0.0),
+        velocity: DVec2::new(2e5, 0.0),
+    };
+
+    assert!(touched(
+        &jupiter,
+        jupiter.position,
+        &impactor,
+        previous,
+        REACH,
+        1e4
+    ));
+}
+
+#[test]
+fn merging_conserves_mass_and_momentum() {
+    let bodies = jupiter_and_io();
+    let (jupiter, mut io) = (bodies[0], bodies[1]);
+    io.velocity += DVec2::new(-3e4, 5e3); // crashing in instead of orbiting
+    let merged = merge(&jupiter, &io);
+
+    assert_eq!(merged.mass, jupiter.mass + io.mass);
+    let momentum = jupiter.mass * jupiter.velocity + io.mass * io.velocity;
+    let error = (merged.mass * merged.velocity - momentum).length();
+    assert!(
+        error <= 1e-12 * momentum.length(),
+        "momentum off by {error}"
+    );
+
+    // the center of mass doesn't jump either
+    let center = (jupiter.mass * jupiter.position + io.mass * io.position) / merged.mass;
+    assert!((merged.position - center).length() <= 1e-12 * IO_POS_X);
+}
+
+#[test]
+fn possible_contacts_include_every_touching_pair() {
+    // bodies moving every which way through a crowded patch, some of them large
+    let count = 200;
+    let bodies = (0..count)
+        .map(|i| BodyState {
+            mass: IO_MASS,
+            position: DVec2::from_angle(i as f64 * 2.4) * 1e9 * (i as f64 / count as f64).sqrt(),
+            velocity: DVec2::ZERO,
+        })
+        .collect::<Vec<_>>();
+    let previous = (0..count)
+        .map(|i| bodies[i].position + DVec2::from_angle(i as f64 * 0.7) * 5e7 * (i % 5) as f64)
+        .collect::<Vec<_>>();
+    let radii = (0..count)
+        .map(|i| if i % 17 == 0 { 5e7 } else { 1e6 })
+        .collect::<Vec<_>>();
+
+    let contacts = possible_contacts(&bodies, &previous, &radii);
+    let mut touching = 0;
+    for i in 0..count {
+        for j in (i + 1)..count {
+            let relative = |k: usize| (previous[k], bodies[k].position);
+            let ((a_from, a_to), (b_from, b_to)) = (relative(i), relative(j));
+            if swept_contact(a_from - b_from, a_to - b_to, radii[i] + radii[j]) {
+                touching += 1;
+                assert!(contacts.contains(&(i, j)), "{i} and {j} were missed");
+            }
+        }
+    }
+    assert!(touching > 0);
+    assert!(contacts.len() < count * (count - 1) / 2 / 10);
+}
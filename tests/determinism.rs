@@ -0,0 +1,77 @@
+use std::{env, fmt::Write, fs, path::Path};
+
+use sun_orbit_simulation::{math::physics::Integrator, scenario::Scenario, simulation::Simulation};
+
+const STEP: f64 = 3600.0;
+const STEPS: u64 = 24 * 365; // a year of hour long steps
+
+// regenerate with `UPDATE_GOLDEN=1 cargo test --test determinism` after intended physics changes
+const GOLDEN: &str = "tests/golden/solar_system.txt";
+
+fn run(integrator: Integrator) -> Simulation {
+    let mut simulation = Simulation::new(&Scenario::solar_system(), STEP);
+    simulation.integrator = integrator;
+    simulation.advance(STEPS);
+    simulation
+}
+
+/// Every body's state as the exact bits of its floats, one body per line.
+fn fingerprint(simulation: &Simulation) -> String {
+    let mut text = String::new();
+    for (name, body) in simulation.names.iter().zip(&simulation.bodies) {
+        let values = [
+            body.position.x,
+            body.position.y,
+            body.velocity.x,
+            body.velocity.y,
+        ];
+        write!(text, "{name}").unwrap();
+        for value in values {
+            write!(text, " {:016x}", value.to_bits()).unwrap();
+        }
+        writeln!(text).unwrap();
+    }
+    text
+}
+
+#[test]
+fn runs_are_bit_identical() {
+    for integrator in Integrator::ALL {
+        assert_eq!(
+            fingerprint(&run(integrator)),
+            fingerprint(&run(integrator)),
+            "{integrator:?} isn't deterministic"
+        );
+    }
+}
+
+#[test]
+fn splitting_a_run_doesnt_change_it() {
+    let mut split = Simulation::new(&Scenario::solar_system(), STEP);
+    for _ in 0..365 {
+        split.advance(24);
+    }
+
+    let whole = run(Integrator::default());
+    assert_eq!(split.elapsed(), whole.elapsed());
+    assert_eq!(fingerprint(&split), fingerprint(&whole));
+}
+
+#[test]
+fn matches_golden_file() {
+    let fingerprint = fingerprint(&run(Integrator::VelocityVerlet));
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(GOLDEN);
+
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &fingerprint).unwrap();
+        return;
+    }
+
+    let golden =
+        fs::read_to_string(&path).expect("golden file missing, create it with UPDATE_GOLDEN=1");
+    assert!(
+        fingerprint == golden,
+        "states differ from {GOLDEN}:\n{fingerprint}"
+    );
+}
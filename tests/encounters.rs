@@ -0,0 +1,56 @@
+use bevy_math::DVec2;
+use sun_orbit_simulation::{
+    constants::{EARTH_MASS, G},
+    math::physics::{BodyState, ForceModel, Integrator, step_bodies},
+};
+
+const SOFTENING: f64 = 1e6;
+
+/// Two Earths dropped from rest towards each other. Softened, they fall through one another and
+/// swing back and forth instead of flinging each other away.
+fn head_on() -> Vec<BodyState> {
+    [-1, 1]
+        .map(|side| BodyState {
+            mass: EARTH_MASS,
+            position: DVec2::new(side as f64 * 5e6, 0.0),
+            velocity: DVec2::ZERO,
+        })
+        .to_vec()
+}
+
+/// Kinetic plus softened potential energy.
+fn energy(bodies: &[BodyState]) -> f64 {
+    let [a, b] = [bodies[0], bodies[1]];
+    let r2 = a.position.distance_squared(b.position) + SOFTENING * SOFTENING;
+    0.5 * a.mass * a.velocity.length_squared() + 0.5 * b.mass * b.velocity.length_squared()
+        - G * a.mass * b.mass / r2.sqrt()
+}
+
+#[test]
+fn softening_keeps_head_on_passes_finite() {
+    let forces = ForceModel {
+        softening: SOFTENING,
+        close_encounters: true,
+        ..Default::default()
+    };
+    let mut bodies = head_on();
+    let start = energy(&bodies);
+
+    // a day, they meet in the middle many times
+    for _ in 0..24 {
+        step_bodies(3600.0, &mut bodies, forces, Integrator::default());
+        for body in &bodies {
+            assert!(body.position.is_finite() && body.velocity.is_finite());
+        }
+    }
+
+    for body in &bodies {
+        assert!(
+            body.position.x.abs() < 5e6 * 1.001,
+            "a body got flung away to {}",
+            body.position.x
+        );
+    }
+    let error = ((energy(&bodies) - start) / start).abs();
+    assert!(error < 1e-6, "energy drifted by {error}");
+}
@@ -0,0 +1,134 @@
+use std::{fs, path::Path};
+
+use bevy_math::DVec3;
+use sun_orbit_simulation::{
+    constants::AU,
+    horizons::{
+        VectorRecord, VectorTable, apply_vector_tables, parse_vector_table, read_vector_table,
+    },
+    scenario::{BodyConfig, Scenario},
+};
+
+// exported from the sun's center, with the vectors on the ecliptic of J2000
+const EARTH: &str = "tests/horizons/earth.txt";
+const EARTH_CSV: &str = "tests/horizons/earth.csv";
+const EARTH_AU: &str = "tests/horizons/earth_au.txt";
+const MOON: &str = "tests/horizons/moon.txt"; // from the earth's center
+const MOON_HELIOCENTRIC: &str = "tests/horizons/moon_heliocentric.txt";
+
+fn read(path: &str) -> VectorTable {
+    read_vector_table(Path::new(path)).unwrap()
+}
+
+fn assert_close(a: DVec3, b: DVec3) {
+    assert!((a - b).length() <= 1e-12 * b.length(), "{a} instead of {b}");
+}
+
+fn assert_same_records(a: &[VectorRecord], b: &[VectorRecord]) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert_eq!(a.julian_date, b.julian_date);
+        assert_close(a.position, b.position);
+        assert_close(a.velocity, b.velocity);
+    }
+}
+
+fn body<'a>(scenario: &'a Scenario, name: &str) -> &'a BodyConfig {
+    scenario
+        .bodies
+        .iter()
+        .find(|body| body.name == name)
+        .unwrap()
+}
+
+#[test]
+fn text_exports_are_read_in_meters() {
+    let table = read(EARTH);
+    assert_eq!(table.target, "Earth");
+    assert_eq!(table.center, "Sun");
+    assert_eq!(table.records.len(), 2);
+
+    let record = table.records[0];
+    assert_eq!(record.julian_date, 2_451_545.0);
+    assert_close(
+        record.position,
+        DVec3::new(
+            -2.649903422886233e10,
+            1.446972967026228e11,
+            -6.112259762793778e5,
+        ),
+    );
+    assert_close(
+        record.velocity,
+        DVec3::new(
+            -2.979426006719171e4,
+            -5.018052874364969e3,
+            1.882834716093609e-1,
+        ),
+    );
+}
+
+#[test]
+fn csv_and_au_per_day_exports_match_the_text_export() {
+    let text = read(EARTH);
+    for path in [EARTH_CSV, EARTH_AU] {
+        let table = read(path);
+        assert_eq!(
+            (table.target.as_str(), table.center.as_str()),
+            ("Earth", "Sun")
+        );
+        assert_same_records(&table.records, &text.records);
+    }
+
+    // a day later the earth is still an AU away
+    let distance = read(EARTH_AU).records[1].position.length();
+    assert!((distance / AU - 0.98).abs() < 0.01);
+}
+
+#[test]
+fn moons_can_be_imported_relative_to_their_planet() {
+    let moon = read(MOON).records[0];
+
+    // on its own, or along with the planet
+    for tables in [vec![read(MOON)], vec![read(EARTH), read(MOON)]] {
+        let mut scenario = Scenario::solar_system();
+        apply_vector_tables(&mut scenario, &tables).unwrap();
+        assert_eq!(body(&scenario, "Moon").position, moon.position.truncate());
+        assert_eq!(body(&scenario, "Moon").velocity, moon.velocity.truncate());
+    }
+
+    // relative to the sun it needs the planet to be imported as well, the table only has the
+    // digits for a relative position good to about a meter
+    let mut scenario = Scenario::solar_system();
+    apply_vector_tables(&mut scenario, &[read(EARTH), read(MOON_HELIOCENTRIC)]).unwrap();
+    let relative = body(&scenario, "Moon").position;
+    assert!(
+        (relative - moon.position.truncate()).length() < 1.0,
+        "moon ended up at {relative}"
+    );
+    assert_eq!(scenario.epoch, 2_451_545.0);
+
+    let mut scenario = Scenario::solar_system();
+    let err = apply_vector_tables(&mut scenario, &[read(MOON_HELIOCENTRIC)]).unwrap_err();
+    assert!(err.to_string().contains("Earth"), "{err}");
+}
+
+#[test]
+fn broken_exports_are_rejected() {
+    let text = fs::read_to_string(EARTH).unwrap();
+    let broken = [
+        (text.replace("$$SOE", ""), "$$SOE"),
+        (text.replace("$$EOE", ""), "$$EOE"),
+        (text.replace(": KM-S", ": AU-S"), "AU-S"),
+        (text.replace("Target body name", "Target"), "target"),
+        (text.replace(" Y = 1.446", " Y = one"), "one"),
+    ];
+
+    for (text, problem) in broken {
+        let err = parse_vector_table(&text).err().unwrap().to_string();
+        assert!(err.contains(problem), "{problem}: {err}");
+    }
+
+    let err = read_vector_table(Path::new("tests/horizons/missing.txt")).err();
+    assert!(err.is_some());
+}
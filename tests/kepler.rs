@@ -0,0 +1,208 @@
+use std::f64::consts::TAU;
+
+use bevy_math::DVec2;
+use sun_orbit_simulation::{
+    constants::{AU, EARTH_MASS, G, SUN_MASS},
+    math::{
+        orbit::compute_orbit,
+        physics::{BodyState, ForceModel, Integrator, orbital_period, step_bodies},
+    },
+    scenario::Scenario,
+};
+
+const STEPS_PER_ORBIT: usize = 1000;
+
+/// Sun and an earth-like planet at the periapsis of an orbit with the given semi-major axis and
+/// eccentricity, in the rest frame of their barycenter.
+fn two_body(semi_major_axis: f64, eccentricity: f64) -> Vec<BodyState> {
+    let mu = G * (SUN_MASS + EARTH_MASS);
+    let periapsis = semi_major_axis * (1.0 - eccentricity);
+    let speed = (mu * (1.0 + eccentricity) / periapsis).sqrt();
+
+    let mut bodies = vec![
+        BodyState {
+            mass: SUN_MASS,
+            position: DVec2::ZERO,
+            velocity: DVec2::ZERO,
+        },
+        BodyState {
+            mass: EARTH_MASS,
+            position: DVec2::new(periapsis, 0.0),
+            velocity: DVec2::new(0.0, speed),
+        },
+    ];
+
+    let momentum = bodies.iter().map(|b| b.mass * b.velocity).sum::<DVec2>();
+    let mass = bodies.iter().map(|b| b.mass).sum::<f64>();
+    for body in &mut bodies {
+        body.velocity -= momentum / mass;
+    }
+    bodies
+}
+
+fn kepler_period(semi_major_axis: f64) -> f64 {
+    TAU * (semi_major_axis.powi(3) / (G * (SUN_MASS + EARTH_MASS))).sqrt()
+}
+
+fn relative_position(bodies: &[BodyState]) -> DVec2 {
+    bodies[1].position - bodies[0].position
+}
+
+fn relative_velocity(bodies: &[BodyState]) -> DVec2 {
+    bodies[1].velocity - bodies[0].velocity
+}
+
+fn energy(bodies: &[BodyState]) -> f64 {
+    let kinetic = bodies
+        .iter()
+        .map(|b| 0.5 * b.mass * b.velocity.length_squared())
+        .sum::<f64>();
+    let potential = -G * bodies[0].mass * bodies[1].mass / relative_position(bodies).length();
+    kinetic + potential
+}
+
+fn assert_closes(semi_major_axis: f64, eccentricity: f64, integrator: Integrator) {
+    let mut bodies = two_body(semi_major_axis, eccentricity);
+    let start = relative_position(&bodies);
+    let period = kepler_period(semi_major_axis);
+
+    let dt = period / STEPS_PER_ORBIT as f64;
+    for _ in 0..STEPS_PER_ORBIT {
+        step_bodies(dt, &mut bodies, ForceModel::default(), integrator);
+    }
+
+    let miss = (relative_position(&bodies) - start).length() / semi_major_axis;
+    assert!(
+        miss < 1e-3,
+        "{integrator:?} e={eccentricity} missed the start by {miss:e} of the semi-major axis"
+    );
+}
+
+#[test]
+fn circular_orbit_closes_after_kepler_period() {
+    for integrator in Integrator::ALL {
+        assert_closes(AU, 0.0, integrator);
+    }
+}
+
+#[test]
+fn elliptical_orbit_closes_after_kepler_period() {
+    for integrator in Integrator::ALL {
+        assert_closes(AU, 0.5, integrator);
+        assert_closes(5.2 * AU, 0.2, integrator);
+    }
+}
+
+#[test]
+fn orbital_period_follows_third_law() {
+    for (semi_major_axis, eccentricity) in [(0.39 * AU, 0.21), (AU, 0.0), (30.0 * AU, 0.6)] {
+        let bodies = two_body(semi_major_axis, eccentricity);
+        let period = orbital_period(
+            relative_position(&bodies),
+            relative_velocity(&bodies),
+            SUN_MASS + EARTH_MASS,
+        );
+
+        let expected = kepler_period(semi_major_axis);
+        assert!(
+            ((period - expected) / expected).abs() < 1e-9,
+            "period {period} instead of {expected}"
+        );
+    }
+}
+
+#[test]
+fn energy_is_conserved() {
+    for integrator in Integrator::ALL {
+        let mut bodies = two_body(AU, 0.5);
+        let initial = energy(&bodies);
+
+        // ten orbits with the physics' own hour long substeps
+        let mut drift: f64 = 0.0;
+        for _ in 0..10 * 365 {
+            step_bodies(86_400.0, &mut bodies, ForceModel::default(), integrator);
+            drift = drift.max(((energy(&bodies) - initial) / initial).abs());
+        }
+
+        assert!(drift < 1e-5, "{integrator:?} energy drifted by {drift:e}");
+    }
+}
+
+#[test]
+fn equal_areas_in_equal_times() {
+    let mut bodies = two_body(AU, 0.6);
+    let period = kepler_period(AU);
+
+    // area swept in each of 12 equal intervals, as a fan of thin triangles
+    let intervals = 12;
+    let steps = 500;
+    let dt = period / (intervals * steps) as f64;
+
+    let areas = (0..intervals)
+        .map(|_| {
+            let mut area = 0.0;
+            for _ in 0..steps {
+                let before = relative_position(&bodies);
+                step_bodies(
+                    dt,
+                    &mut bodies,
+                    ForceModel::default(),
+                    Integrator::default(),
+                );
+                area += 0.5 * before.perp_dot(relative_position(&bodies));
+            }
+            area
+        })
+        .collect::<Vec<_>>();
+
+    let mean = areas.iter().sum::<f64>() / areas.len() as f64;
+    for area in areas {
+        assert!(
+            ((area - mean) / mean).abs() < 1e-3,
+            "swept {area:e} instead of {mean:e}"
+        );
+    }
+}
+
+#[test]
+fn compute_orbit_closes_for_every_planet() {
+    let scenario = Scenario::solar_system();
+
+    for body in scenario.bodies.iter().filter(|body| body.parent.is_none()) {
+        let points = compute_orbit(body.position, body.velocity, SUN_MASS + body.mass).unwrap();
+        assert!(points.len() > 2, "{} has no orbit", body.name);
+
+        // consecutive points are spread out evenly, the gap closing the orbit shouldn't be
+        // wider than the others
+        let widest = points
+            .windows(2)
+            .map(|pair| pair[0].distance(pair[1]))
+            .fold(0.0, f64::max);
+        let first = points[0];
+        let last = points[points.len() - 1];
+
+        assert!(
+            first.distance(body.position) <= widest,
+            "{} orbit starts away from the planet",
+            body.name
+        );
+        assert!(
+            last.distance(first) <= 1.5 * widest,
+            "{} orbit doesn't close, gap {:e} m with points {widest:e} m apart",
+            body.name,
+            last.distance(first)
+        );
+    }
+}
+
+#[test]
+fn compute_orbit_skips_unbound_bodies() {
+    let position = DVec2::new(AU, 0.0);
+    let escape_speed = (2.0 * G * SUN_MASS / AU).sqrt();
+
+    // parabolic, hyperbolic, and nothing to orbit at all
+    for (speed, mass) in [(escape_speed, SUN_MASS), (1e5, SUN_MASS), (3e4, 0.0)] {
+        let velocity = DVec2::new(0.0, speed);
+        assert!(compute_orbit(position, velocity, mass).is_none());
+    }
+}
@@ -0,0 +1,60 @@
+use bevy_math::DVec2;
+use sun_orbit_simulation::{
+    constants::{AU, EARTH_MASS, G, SUN_MASS},
+    math::{
+        particles::ParticleState,
+        physics::{BodyState, ForceModel, Integrator, track_bodies},
+    },
+};
+
+const PERIAPSIS: f64 = 1e7; // m, from the earth
+const ECCENTRICITY: f64 = 0.9;
+
+/// The sun, the earth, and a body of 1 kg on an eccentric orbit around the earth, close enough
+/// at the periapsis for the steps to be shortened.
+fn bodies() -> Vec<BodyState> {
+    let earth_speed = (G * SUN_MASS / AU).sqrt();
+    let periapsis_speed = (G * EARTH_MASS * (1.0 + ECCENTRICITY) / PERIAPSIS).sqrt();
+    vec![
+        BodyState {
+            mass: SUN_MASS,
+            position: DVec2::ZERO,
+            velocity: DVec2::ZERO,
+        },
+        BodyState {
+            mass: EARTH_MASS,
+            position: DVec2::new(AU, 0.0),
+            velocity: DVec2::new(0.0, earth_speed),
+        },
+        BodyState {
+            mass: 1.0,
+            position: DVec2::new(AU + PERIAPSIS, 0.0),
+            velocity: DVec2::new(0.0, earth_speed + periapsis_speed),
+        },
+    ]
+}
+
+#[test]
+fn particles_follow_the_bodies_through_encounters() {
+    // softened, so the body doesn't pull on the particle right on top of it
+    let forces = ForceModel {
+        softening: 1e3,
+        close_encounters: true,
+        ..Default::default()
+    };
+    let mut bodies = bodies();
+    let mut particle = ParticleState {
+        position: bodies[2].position,
+        velocity: bodies[2].velocity,
+    };
+
+    // ten days, a few swings past the earth
+    for _ in 0..10 {
+        let track = track_bodies(86_400.0, &mut bodies, forces, Integrator::VelocityVerlet);
+        track.advance(&mut particle, forces.softening);
+    }
+
+    let error = particle.position.distance(bodies[2].position);
+    let distance = bodies[2].position.distance(bodies[1].position);
+    assert!(error < 1e-8 * distance, "the particle is {error} m off");
+}